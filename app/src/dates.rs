@@ -0,0 +1,322 @@
+//! Dates as people type them, e.g. `2026-11-12`, `next thursday`, `+3w`, `nov 10..nov 30`
+//! or `every wed in december`.
+
+use calgary_central_library::library_today;
+use chrono::{format::ParseErrorKind, Datelike, Days, Month, Months, NaiveDate, Weekday};
+use thiserror::Error;
+
+const EXAMPLES: &str =
+    "e.g. 2026-11-12, today, tomorrow, thursday, next thursday, +3d, +3w, +1m or nov 12";
+const RANGE_EXAMPLES: &str = "e.g. nov 10..nov 30 or every wed in december";
+
+/// Upcoming days, as a single command-line argument.
+#[derive(Clone, Debug)]
+pub(crate) struct Dates(pub(crate) Vec<NaiveDate>);
+
+#[derive(Error, Debug, PartialEq)]
+pub(crate) enum DateError {
+    #[error("{0:?} is not a date that can be understood ({EXAMPLES})")]
+    Unrecognized(String),
+    #[error("{0:?} is not a day of the calendar")]
+    NoSuchDay(String),
+    #[error("{date} has already passed, today is {today}")]
+    Past { date: NaiveDate, today: NaiveDate },
+    #[error("The range ends on {to}, before it starts on {from}")]
+    BackwardRange { from: NaiveDate, to: NaiveDate },
+    #[error("{0:?} doesn't contain any upcoming day ({RANGE_EXAMPLES})")]
+    NoDays(String),
+}
+
+/// Lower case with single spaces, so that `Next  Thursday` is `next thursday`.
+fn normalize(input: &str) -> String {
+    input
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn weekday(word: &str) -> Option<Weekday> {
+    match word {
+        "tues" => Some(Weekday::Tue),
+        "thur" | "thurs" => Some(Weekday::Thu),
+        _ => word.parse().ok(),
+    }
+}
+
+fn month(word: &str) -> Option<Month> {
+    match word {
+        "sept" => Some(Month::September),
+        _ => word.parse().ok(),
+    }
+}
+
+/// The first `weekday` on or after `from`.
+fn next_weekday(from: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let days = (7 + weekday.num_days_from_monday() - from.weekday().num_days_from_monday()) % 7;
+    from + Days::new(days.into())
+}
+
+/// `+3d`, `+3w` or `+3m`.
+fn relative(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let rest = input.strip_prefix('+')?.trim_start();
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let count: u32 = rest[..digits].parse().ok()?;
+    match rest[digits..].trim() {
+        "d" | "day" | "days" => today.checked_add_days(Days::new(count.into())),
+        "w" | "week" | "weeks" => today.checked_add_days(Days::new(7 * u64::from(count))),
+        "m" | "month" | "months" => today.checked_add_months(Months::new(count)),
+        _ => None,
+    }
+}
+
+/// `nov 12`, `november 12`, `12 nov` or any of them followed by a year, e.g. `nov 12, 2026`.
+/// Without a year, the next such day on or after `today`.
+fn month_day(input: &str, today: NaiveDate) -> Option<Result<NaiveDate, DateError>> {
+    let words: Vec<_> = input.split([' ', ',']).filter(|w| !w.is_empty()).collect();
+    let (month, day, year) = match words[..] {
+        [a, b] | [a, b, _] => match (month(a), month(b)) {
+            (Some(month), None) => (month, b.parse::<u32>().ok()?, words.get(2)),
+            (None, Some(month)) => (month, a.parse::<u32>().ok()?, words.get(2)),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let month = month.number_from_month();
+    let no_such_day = || DateError::NoSuchDay(input.to_owned());
+    let date = match year {
+        Some(year) => {
+            let year = year.parse().ok()?;
+            NaiveDate::from_ymd_opt(year, month, day).ok_or_else(no_such_day)
+        }
+        None => NaiveDate::from_ymd_opt(today.year(), month, day)
+            .filter(|date| *date >= today)
+            .or_else(|| NaiveDate::from_ymd_opt(today.year() + 1, month, day))
+            .ok_or_else(no_such_day),
+    };
+    Some(date)
+}
+
+/// Parses a single day, which may have passed.
+pub(crate) fn parse_date(input: &str, today: NaiveDate) -> Result<NaiveDate, DateError> {
+    let input = normalize(input);
+    let unrecognized = || DateError::Unrecognized(input.clone());
+    match input.as_str() {
+        "today" => return Ok(today),
+        "tomorrow" => return today.succ_opt().ok_or_else(unrecognized),
+        "yesterday" => return today.pred_opt().ok_or_else(unrecognized),
+        _ => {}
+    }
+    if input.starts_with(|c: char| c.is_ascii_digit()) && input.contains('-') {
+        return NaiveDate::parse_from_str(&input, "%Y-%m-%d").map_err(|e| match e.kind() {
+            ParseErrorKind::OutOfRange | ParseErrorKind::Impossible => {
+                DateError::NoSuchDay(input.clone())
+            }
+            _ => unrecognized(),
+        });
+    }
+    if input.starts_with('+') {
+        return relative(&input, today).ok_or_else(unrecognized);
+    }
+    let (next, day) = match input.split_once(' ') {
+        Some(("next", day)) => (true, day),
+        Some(("this", day)) => (false, day),
+        _ => (false, input.as_str()),
+    };
+    if let Some(weekday) = weekday(day) {
+        let from = if next {
+            today.succ_opt().unwrap()
+        } else {
+            today
+        };
+        return Ok(next_weekday(from, weekday));
+    }
+    month_day(&input, today).unwrap_or_else(|| Err(unrecognized()))
+}
+
+/// Parses a day that hasn't passed yet.
+pub(crate) fn parse_upcoming_date(input: &str, today: NaiveDate) -> Result<NaiveDate, DateError> {
+    let date = parse_date(input, today)?;
+    if date < today {
+        return Err(DateError::Past { date, today });
+    }
+    Ok(date)
+}
+
+/// `every wed in december`, optionally followed by a year.
+fn every_weekday(input: &str, today: NaiveDate) -> Option<Result<Vec<NaiveDate>, DateError>> {
+    let rest = input.strip_prefix("every ")?;
+    let (day, month_and_year) = rest.split_once(" in ")?;
+    let weekday = weekday(day).or_else(|| weekday(day.strip_suffix('s')?))?;
+    let (month_name, year) = match month_and_year.split_once(' ') {
+        Some((month, year)) => (month, Some(year.parse::<i32>().ok()?)),
+        None => (month_and_year, None),
+    };
+    let month = month(month_name)?.number_from_month();
+    // `None` for a year that the calendar doesn't reach
+    let in_month = |year| {
+        let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let dates = first
+            .iter_days()
+            .take_while(|date| date.month() == month)
+            .filter(|date| date.weekday() == weekday && *date >= today)
+            .collect::<Vec<_>>();
+        Some(dates)
+    };
+    let dates = match year {
+        Some(year) => in_month(year),
+        // A month that is over is the one of next year
+        None => in_month(today.year())
+            .filter(|dates| !dates.is_empty())
+            .or_else(|| in_month(today.year() + 1)),
+    };
+    let Some(dates) = dates else {
+        return Some(Err(DateError::NoSuchDay(input.to_owned())));
+    };
+    if dates.is_empty() {
+        return Some(Err(DateError::NoDays(input.to_owned())));
+    }
+    Some(Ok(dates))
+}
+
+/// Parses upcoming days: a single one, an inclusive range like `nov 10..nov 30`
+/// or every weekday of a month like `every wed in december`.
+pub(crate) fn parse_upcoming_dates(
+    input: &str,
+    today: NaiveDate,
+) -> Result<Vec<NaiveDate>, DateError> {
+    let input = normalize(input);
+    if let Some((from, to)) = input.split_once("..") {
+        let from = parse_upcoming_date(from, today)?;
+        let to = parse_upcoming_date(to, today)?;
+        if to < from {
+            return Err(DateError::BackwardRange { from, to });
+        }
+        return Ok(from.iter_days().take_while(|date| *date <= to).collect());
+    }
+    if let Some(dates) = every_weekday(&input, today) {
+        return dates;
+    }
+    parse_upcoming_date(&input, today).map(|date| vec![date])
+}
+
+/// The library's today, so that a date isn't taken for a past one in the evening in Calgary
+/// when the machine is in another timezone.
+fn today() -> NaiveDate {
+    library_today()
+}
+
+/// A value parser for the arguments that take any day.
+pub(crate) fn any_date(input: &str) -> Result<NaiveDate, DateError> {
+    parse_date(input, today())
+}
+
+/// A value parser for the arguments that take an upcoming day.
+pub(crate) fn upcoming_date(input: &str) -> Result<NaiveDate, DateError> {
+    parse_upcoming_date(input, today())
+}
+
+/// A value parser for the arguments that take upcoming days.
+pub(crate) fn upcoming_dates(input: &str) -> Result<Dates, DateError> {
+    parse_upcoming_dates(input, today()).map(Dates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Monday.
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 19).unwrap()
+    }
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    #[test]
+    fn single_dates() {
+        let parse = |input| parse_upcoming_date(input, today());
+        assert_eq!(parse("2026-11-12"), Ok(date(11, 12)));
+        assert_eq!(parse("Tomorrow"), Ok(date(10, 20)));
+        assert_eq!(parse("thursday"), Ok(date(10, 22)));
+        assert_eq!(parse("monday"), Ok(today()));
+        assert_eq!(parse("next  Monday"), Ok(date(10, 26)));
+        assert_eq!(parse("+3w"), Ok(date(11, 9)));
+        assert_eq!(parse("+ 2 days"), Ok(date(10, 21)));
+        assert_eq!(parse("+1m"), Ok(date(11, 19)));
+        assert_eq!(parse("nov 12"), Ok(date(11, 12)));
+        assert_eq!(parse("12 November"), Ok(date(11, 12)));
+        assert_eq!(
+            parse("jan 5"),
+            Ok(NaiveDate::from_ymd_opt(2027, 1, 5).unwrap())
+        );
+    }
+
+    #[test]
+    fn helpful_errors() {
+        let parse = |input| parse_upcoming_date(input, today());
+        assert_eq!(
+            parse("2026-10-01"),
+            Err(DateError::Past {
+                date: date(10, 1),
+                today: today()
+            })
+        );
+        assert_eq!(
+            parse("feb 30 2027"),
+            Err(DateError::NoSuchDay("feb 30 2027".to_owned()))
+        );
+        assert_eq!(
+            parse("2026-02-30"),
+            Err(DateError::NoSuchDay("2026-02-30".to_owned()))
+        );
+        assert_eq!(
+            parse("someday"),
+            Err(DateError::Unrecognized("someday".to_owned()))
+        );
+        assert_eq!(
+            parse_upcoming_dates("nov 30..nov 10", today()),
+            Err(DateError::BackwardRange {
+                from: date(11, 30),
+                to: date(11, 10)
+            })
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        let dates = parse_upcoming_dates("nov 10..nov 30", today()).unwrap();
+        assert_eq!(dates.len(), 21);
+        assert_eq!(dates[0], date(11, 10));
+
+        let dates = parse_upcoming_dates("every wed in december", today()).unwrap();
+        assert_eq!(
+            dates,
+            [
+                date(12, 2),
+                date(12, 9),
+                date(12, 16),
+                date(12, 23),
+                date(12, 30)
+            ]
+        );
+
+        // This October's Mondays from today on
+        let dates = parse_upcoming_dates("every mondays in october", today()).unwrap();
+        assert_eq!(dates, [today(), date(10, 26)]);
+
+        assert_eq!(
+            parse_upcoming_dates("every wed in january 2020", today()),
+            Err(DateError::NoDays("every wed in january 2020".to_owned()))
+        );
+        assert_eq!(
+            parse_upcoming_dates("every wed in january 300000", today()),
+            Err(DateError::NoSuchDay(
+                "every wed in january 300000".to_owned()
+            ))
+        );
+    }
+}
@@ -1,62 +1,797 @@
 // TODO: consider using futures::future::join_all for async iteration
 
-use std::env;
+mod dates;
+mod output;
+mod source;
+mod tui;
 
-use calgary_central_library::{AsyncClient, Availability, Client, Room};
-use chrono::{DateTime, NaiveDate};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-// let's set up the sequence of steps we want the browser to take
-#[tokio::main]
-async fn main() {
-    let host =
-        env::var("CHROMEDRIVER_HOST").expect("Environment variable CHROMEDRIVER_HOST is not set");
-    let port =
-        env::var("CHROMEDRIVER_PORT").expect("Environment variable CHROMEDRIVER_PORT is not set");
-    let port = port
-        .parse::<u16>()
-        .expect("Failed to parse CHROMEDRIVER_PORT as a u16");
-    println!("Connecting to WebDriver at http://{host}:{port}...");
-    let c = Client::new((&host, port)).await.unwrap();
-
-    let now: DateTime<chrono::Local> = chrono::Local::now();
-    let today: NaiveDate = now.date_naive();
+use calgary_central_library::{
+    booking_url, default_database_path, library_today, upcoming_weekdays, Availability, Booking,
+    Config, Digest, Notification, Notifier, Profile, Room, Schedule, SmtpConfig, SmtpDigestSender,
+    Snapshot, SnapshotStore, SqliteSnapshotStore, SqliteSnapshotStoreError, StdoutNotifier,
+    WatchRule, Watcher, WebhookConfig, WebhookError, WebhookNotifier,
+};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, Weekday};
+use clap::{Parser, Subcommand};
+use dates::{DateError, Dates};
+use output::Format;
+use serde::Deserialize;
+use source::{Backend, Reconnecting, Source};
+
+/// Checks which rooms of the Calgary Central Library are available.
+///
+/// Without a subcommand, asks for the day and the number of attendees interactively.
+///
+/// The WebDriver server, the crawling server and the defaults of the organizer's profile
+/// are taken from the shared configuration file unless the flags say otherwise.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// How the rooms are printed.
+    #[arg(long, global = true, value_enum, default_value_t = Format::Table)]
+    format: Format,
+    /// The profile of the shared configuration, e.g. `meetups`. The default profile if unset.
+    #[arg(long, global = true, env = "BOOKING_ROOMS_PROFILE")]
+    profile: Option<String>,
+    /// The host of the WebDriver server, e.g. chromedriver.
+    #[arg(long, global = true, env = "CHROMEDRIVER_HOST")]
+    web_driver_host: Option<String>,
+    /// The port of the WebDriver server.
+    #[arg(long, global = true, env = "CHROMEDRIVER_PORT")]
+    web_driver_port: Option<u16>,
+    /// The URL of a running crawling server, e.g. `http://localhost:3000`,
+    /// that checks the rooms instead of a WebDriver server.
+    #[arg(long, global = true, env = "BOOKING_ROOMS_SERVER")]
+    server: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Checks which rooms are available on a day. The missing arguments are asked for.
+    Search {
+        /// The day to check, e.g. `2026-11-12`, `next thursday` or `+3w`.
+        #[arg(long, value_parser = dates::upcoming_date)]
+        date: Option<NaiveDate>,
+        /// The expected number of attendees.
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..))]
+        group_size: Option<u8>,
+    },
+    /// Shows the rooms as a grid of half-hour slots that can be browsed day by day.
+    Tui {
+        /// The first day to show, e.g. `2026-11-12` or `next thursday`. Today by default.
+        #[arg(long, value_parser = dates::upcoming_date)]
+        date: Option<NaiveDate>,
+        /// The expected number of attendees. The one of the profile by default.
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..))]
+        group_size: Option<u8>,
+    },
+    /// Checks which rooms are available on every day of a range.
+    Scan {
+        /// The days to check, e.g. `nov 10..nov 30` or `every wed in december`.
+        #[arg(
+            long,
+            value_parser = dates::upcoming_dates,
+            conflicts_with_all = ["from", "to"],
+            required_unless_present = "from"
+        )]
+        dates: Option<Dates>,
+        /// The first day to check, e.g. `2026-11-10` or `tomorrow`.
+        #[arg(long, value_parser = dates::upcoming_date, requires = "to")]
+        from: Option<NaiveDate>,
+        /// The last day to check, included.
+        #[arg(long, value_parser = dates::upcoming_date, requires = "from")]
+        to: Option<NaiveDate>,
+        /// The expected number of attendees. The one of the profile by default.
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..))]
+        group_size: Option<u8>,
+    },
+    /// Checks that a room is free and records its booking once it's made on the library's site.
+    ///
+    /// The library requires a patron login to book, so the booking itself is made in the
+    /// browser: without `--confirmation`, prints the booking page to open.
+    Book {
+        /// The day of the booking, e.g. `2026-11-12` or `next thursday`.
+        #[arg(long, value_parser = dates::upcoming_date)]
+        date: NaiveDate,
+        /// The beginning of the title of the room, e.g. `3-20A`.
+        #[arg(long)]
+        room: String,
+        /// When the booking starts, e.g. `18:00`.
+        #[arg(long)]
+        from: NaiveTime,
+        /// When the booking ends, e.g. `21:00`.
+        #[arg(long)]
+        to: NaiveTime,
+        /// The expected number of attendees. The one of the profile by default.
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..))]
+        group_size: Option<u8>,
+        /// The confirmation id that the library gave to the booking, to record it.
+        #[arg(long)]
+        confirmation: Option<String>,
+        /// A watch rules file whose webhooks also hear about the recorded booking,
+        /// besides the ones of the profile.
+        #[arg(long, requires = "confirmation")]
+        rules: Option<PathBuf>,
+    },
+    /// Lists the recorded bookings.
+    Bookings {
+        /// Include the bookings of past days.
+        #[arg(long)]
+        all: bool,
+    },
+    /// Removes a booking from the recorded ones.
+    Cancel {
+        /// The confirmation id of the booking.
+        confirmation: String,
+    },
+    /// Shows which time slots opened or closed since the rooms were last checked.
+    Diff {
+        /// The day to check, e.g. `2026-11-12` or `next thursday`. Past days need `--recorded`.
+        #[arg(long, value_parser = dates::any_date)]
+        date: NaiveDate,
+        /// The expected number of attendees. The one of the profile by default.
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..))]
+        group_size: Option<u8>,
+        /// Compare the two most recent recorded checks instead of checking again.
+        #[arg(long)]
+        recorded: bool,
+    },
+    /// Checks the rooms on a schedule and reports when a watch rule becomes satisfiable.
+    Watch {
+        /// The TOML file with the `[[rules]]` to watch. See `watch.example.toml`.
+        #[arg(long)]
+        rules: PathBuf,
+        /// How often the rooms are checked, e.g. `15m`.
+        #[arg(long, default_value = "15m", value_parser = humantime::parse_duration)]
+        interval: Duration,
+        /// Up to how much is added to every interval at random.
+        #[arg(long, default_value = "2m", value_parser = humantime::parse_duration)]
+        jitter: Duration,
+        /// The longest interval after consecutive failures.
+        #[arg(long, default_value = "2h", value_parser = humantime::parse_duration)]
+        max_backoff: Duration,
+    },
+    /// Emails a digest of the free rooms on the candidate meetup dates every week.
+    Digest {
+        /// The TOML file with the recipients and the schedule. See `digest.example.toml`.
+        #[arg(long)]
+        config: PathBuf,
+        /// Send a single digest right away instead of waiting for the schedule.
+        #[arg(long)]
+        now: bool,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WatchFile {
+    rules: Vec<WatchRule>,
+    /// The webhooks that receive the notifications besides the standard output.
+    #[serde(default)]
+    webhooks: Vec<WebhookConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DigestFile {
+    /// The one of the profile if unset.
+    #[serde(default)]
+    group_size: Option<u8>,
+    /// The candidate meetup dates are the next `dates` days that fall on these weekdays.
+    meetup_weekdays: Vec<Weekday>,
+    dates: usize,
+    /// When the digest is sent every week, in local time.
+    send_on: Weekday,
+    send_at: NaiveTime,
+    /// The one of the profile if unset.
+    #[serde(default)]
+    smtp: Option<SmtpConfig>,
+}
+
+/// Prints the notifications and sends them to every webhook.
+struct Notifiers {
+    webhooks: Vec<WebhookNotifier>,
+}
+
+impl Notifier for Notifiers {
+    type NotifyError = Vec<WebhookError>;
+
+    async fn notify(&self, notification: &Notification) -> Result<(), Self::NotifyError> {
+        let Ok(()) = StdoutNotifier.notify(notification).await;
+        let mut errors = Vec::new();
+        for webhook in &self.webhooks {
+            if let Err(e) = webhook.notify(notification).await {
+                errors.push(e);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Opens the database of past scrapes. The CLI works without it.
+fn open_snapshot_store() -> Option<SqliteSnapshotStore> {
+    let path = default_database_path()?;
+    match SqliteSnapshotStore::open(&path) {
+        Ok(store) => Some(store),
+        Err(e) => {
+            eprintln!("Past scrapes won't be recorded, failed to open {path:?}: {e}");
+            None
+        }
+    }
+}
+
+/// Like [`open_snapshot_store`], for the commands that can't work without it.
+fn require_snapshot_store(purpose: &str) -> SqliteSnapshotStore {
+    open_snapshot_store().unwrap_or_else(|| {
+        eprintln!("{purpose} requires the database of past checks");
+        std::process::exit(1);
+    })
+}
+
+/// The result of a query of the database of past checks, or exits after printing why it failed,
+/// e.g. because the database is locked.
+fn database_or_exit<T>(result: Result<T, SqliteSnapshotStoreError>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("Failed to use the database of past checks: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Connects to the backend, or exits after printing why it can't.
+async fn connect(backend: &Backend) -> Source {
+    backend.connect().await.unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    })
+}
+
+/// Checks the rooms and closes the source, or exits after printing why they couldn't be checked.
+async fn scrape_once(
+    source: Source,
+    snapshots: Option<&SqliteSnapshotStore>,
+    date: NaiveDate,
+    group_size: u8,
+) -> Snapshot {
+    let snapshot = source.scrape(snapshots, date, group_size).await;
+    source.close().await;
+    snapshot.unwrap_or_else(|e| {
+        eprintln!("Failed to check the rooms for {date} and {group_size} people: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Prints the rooms with the ones that the profile prefers first.
+fn print_rooms(mut snapshots: Vec<Snapshot>, profile: &Profile, format: Format) {
+    for snapshot in &mut snapshots {
+        profile.rank(&mut snapshot.rooms);
+    }
+    print!("{}", output::rooms(&snapshots, format));
+}
+
+fn print_bookings(bookings: &[Booking], format: Format) {
+    if bookings.is_empty() {
+        eprintln!("No bookings have been recorded");
+    }
+    print!("{}", output::bookings(bookings, format));
+}
+
+async fn diff(
+    backend: &Backend,
+    profile: &Profile,
+    date: NaiveDate,
+    group_size: u8,
+    recorded: bool,
+    format: Format,
+) {
+    if !recorded && date < library_today() {
+        eprintln!("{date} has already passed, so only its recorded checks can be compared with `--recorded`");
+        std::process::exit(1);
+    }
+    let snapshots = require_snapshot_store("Comparing the rooms");
+    let (older, newer) = if recorded {
+        let mut history = database_or_exit(snapshots.history(date, group_size, 2));
+        if history.len() < 2 {
+            eprintln!("The rooms for {date} and {group_size} people have been checked fewer than two times");
+            std::process::exit(1);
+        }
+        let older = history.pop().unwrap();
+        let newer = history.pop().unwrap();
+        (older, newer)
+    } else {
+        let older = database_or_exit(snapshots.latest(date, Some(group_size)));
+        let newer = scrape_once(connect(backend).await, Some(&snapshots), date, group_size).await;
+        let Some(older) = older else {
+            eprintln!(
+                "The rooms for {date} and {group_size} people are checked for the first time:"
+            );
+            print_rooms(vec![newer], profile, format);
+            return;
+        };
+        (older, newer)
+    };
+    print!("{}", output::changes(&older, &newer, format));
+}
+
+/// Reads and parses a TOML file, or exits after printing why it can't.
+fn read_toml_file<T: serde::de::DeserializeOwned>(path: &Path, contents: &str) -> T {
+    let file = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read the {contents} from {path:?}: {e}");
+        std::process::exit(1);
+    });
+    toml::from_str(&file).unwrap_or_else(|e| {
+        eprintln!("Failed to parse the {contents} from {path:?}: {e}");
+        std::process::exit(1);
+    })
+}
+
+async fn watch(backend: &Backend, profile: &Profile, path: PathBuf, schedule: Schedule) {
+    let WatchFile {
+        rules,
+        mut webhooks,
+    } = read_toml_file(&path, "watch rules");
+    webhooks.extend(profile.webhooks.iter().cloned());
+    let snapshots = open_snapshot_store();
+    // Connected on the first check and again whenever a check fails
+    let source = tokio::sync::Mutex::new(Reconnecting::new(backend.clone()));
+
+    println!("Watching {} rule(s)", rules.len());
+    let notifiers = Notifiers {
+        webhooks: webhooks.into_iter().map(WebhookNotifier::new).collect(),
+    };
+    let watcher = Watcher::new(rules, schedule, notifiers);
+    let (source_ref, snapshots) = (&source, snapshots.as_ref());
+    watcher
+        .run(
+            move |date, group_size| async move {
+                let mut source = source_ref.lock().await;
+                let snapshot = source.scrape(snapshots, date, group_size).await;
+                snapshot.map(|snapshot| snapshot.rooms)
+            },
+            |e| eprintln!("{e}"),
+        )
+        .await;
+    println!("Every watched date has passed");
+
+    source.into_inner().close().await;
+}
+
+/// The first time after `now` that falls on `weekday` at `at`.
+fn next_occurrence(now: DateTime<Local>, weekday: Weekday, at: NaiveTime) -> DateTime<Local> {
+    (0..=7)
+        .map(|days| now.date_naive() + chrono::Duration::try_days(days).unwrap())
+        .filter(|date| date.weekday() == weekday)
+        .filter_map(|date| date.and_time(at).and_local_timezone(Local).earliest())
+        .find(|time| *time > now)
+        .expect("every weekday occurs within 8 days")
+}
 
+/// Scrapes the candidate dates, falling back to the last known rooms, and emails the digest.
+async fn send_digest(
+    backend: &Backend,
+    profile: &Profile,
+    config: &DigestFile,
+    group_size: u8,
+    sender: &SmtpDigestSender,
+) {
+    let snapshots = open_snapshot_store();
+    let dates = upcoming_weekdays(library_today(), &config.meetup_weekdays, config.dates);
+    let c = match backend.connect().await {
+        Ok(c) => Some(c),
+        Err(e) => {
+            eprintln!("{e}, the last known rooms are sent");
+            None
+        }
+    };
+    let mut days = Vec::new();
+    for date in dates {
+        let scraped = match &c {
+            Some(c) => c
+                .scrape(snapshots.as_ref(), date, group_size)
+                .await
+                .inspect_err(|e| eprintln!("Failed to check the rooms for {date}: {e}"))
+                .ok(),
+            None => None,
+        };
+        let snapshot = scraped.or_else(|| {
+            snapshots
+                .as_ref()
+                .and_then(|store| store.latest(date, Some(group_size)).ok().flatten())
+        });
+        days.extend(snapshot.map(|mut snapshot| {
+            profile.rank(&mut snapshot.rooms);
+            snapshot
+        }));
+    }
+    if let Some(c) = c {
+        c.close().await;
+    }
+    let digest = Digest { group_size, days };
+    match sender.send(&digest).await {
+        Ok(()) => println!("Sent {:?}", digest.subject()),
+        Err(e) => eprintln!("Failed to send the digest: {e}"),
+    }
+}
+
+async fn digest(backend: &Backend, profile: &Profile, path: PathBuf, now: bool) {
+    let config: DigestFile = read_toml_file(&path, "digest configuration");
+    let group_size = config.group_size.unwrap_or(profile.group_size);
+    let Some(smtp) = config.smtp.as_ref().or(profile.smtp.as_ref()) else {
+        eprintln!("Neither {path:?} nor the profile configures the SMTP server");
+        std::process::exit(1);
+    };
+    let sender = SmtpDigestSender::new(smtp).unwrap_or_else(|e| {
+        eprintln!("Invalid SMTP configuration: {e}");
+        std::process::exit(1);
+    });
+    if now {
+        send_digest(backend, profile, &config, group_size, &sender).await;
+        return;
+    }
+    loop {
+        let next = next_occurrence(Local::now(), config.send_on, config.send_at);
+        println!("The next digest is sent at {next}");
+        let delay = (next - Local::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(delay).await;
+        send_digest(backend, profile, &config, group_size, &sender).await;
+    }
+}
+
+/// Reads a line from the standard input after asking `question`.
+fn prompt(question: &str) -> String {
+    eprintln!("{question}");
     let mut s = String::new();
+    if std::io::stdin().read_line(&mut s).unwrap() == 0 {
+        eprintln!("No answer was given");
+        std::process::exit(1);
+    }
+    s.trim().to_owned()
+}
+
+async fn search(
+    backend: &Backend,
+    profile: &Profile,
+    date: Option<NaiveDate>,
+    group_size: Option<u8>,
+    format: Format,
+) {
+    let snapshots = open_snapshot_store();
+
+    let day = date.unwrap_or_else(|| loop {
+        let answer =
+            prompt("Which day would you like to check? (e.g. 2026-11-12, next thursday or +3d)");
+        match dates::upcoming_date(&answer) {
+            Ok(day) => {
+                eprintln!("Checking availability for {}", day.format("%A, %B %-d, %Y"));
+                break day;
+            }
+            Err(e) => eprintln!("{e}"),
+        }
+    });
+
+    let attendance = group_size.unwrap_or_else(|| loop {
+        let answer = prompt(&format!(
+            "What's the expected number of attendees? (Default: {})",
+            profile.group_size
+        ));
+        if answer.is_empty() {
+            break profile.group_size;
+        }
+        match answer.parse::<u8>() {
+            Ok(attendance) if attendance > 0 => break attendance,
+            _ => eprintln!("{answer:?} is not a number of attendees between 1 and 255"),
+        }
+    });
 
-    println!("For how many days ahead would you like to check the availability?");
-    let days_ahead = {
-        std::io::stdin().read_line(&mut s).unwrap();
-        s.trim()
-            .parse::<u8>()
-            .expect("Failed to parse the number of days ahead")
+    let c = match backend.connect().await {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{e}");
+            let latest = snapshots
+                .as_ref()
+                .and_then(|store| store.latest(day, Some(attendance)).ok().flatten());
+            match latest {
+                Some(snapshot) => {
+                    eprintln!(
+                        "Showing the last known rooms, scraped at {}",
+                        snapshot.scraped_at
+                    );
+                    print_rooms(vec![snapshot], profile, format);
+                }
+                None => eprintln!("No rooms have been recorded for {day} and {attendance} people"),
+            }
+            std::process::exit(1);
+        }
     };
 
-    s.clear();
+    let snapshot = scrape_once(c, snapshots.as_ref(), day, attendance).await;
+    print_rooms(vec![snapshot], profile, format);
+}
 
-    let mut day = today;
-    for _ in 0..days_ahead {
-        day = day.succ_opt().unwrap();
+async fn scan(
+    backend: &Backend,
+    profile: &Profile,
+    dates: Vec<NaiveDate>,
+    group_size: u8,
+    format: Format,
+) {
+    let snapshots = open_snapshot_store();
+    let c = connect(backend).await;
+    let mut days = Vec::new();
+    let mut failed = false;
+    for date in dates {
+        eprintln!("Checking availability for {date}");
+        match c.scrape(snapshots.as_ref(), date, group_size).await {
+            Ok(snapshot) => days.push(snapshot),
+            Err(e) => {
+                eprintln!("Failed to check the rooms for {date}: {e}");
+                failed = true;
+            }
+        }
     }
+    c.close().await;
+    // The days that could be checked are printed either way
+    print_rooms(days, profile, format);
+    if failed {
+        std::process::exit(1);
+    }
+}
 
-    println!("Checking availability for {day:?}");
+/// The only room whose title starts with `prefix`.
+fn find_room<'a>(
+    rooms: &'a [(Room, Availability)],
+    prefix: &str,
+) -> Result<&'a (Room, Availability), String> {
+    let matching: Vec<_> = rooms
+        .iter()
+        .filter(|(room, _)| room.title().starts_with(prefix))
+        .collect();
+    match matching[..] {
+        [room] => Ok(room),
+        [] => Err(format!("No room has a title that starts with {prefix:?}")),
+        _ => Err(format!(
+            "{prefix:?} could be any of {}",
+            matching
+                .iter()
+                .map(|(room, _)| format!("{:?}", room.title()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
 
-    println!("What's the expected number of attendees? (Default: 10)");
-    let attendance: u8 = 'attendance: {
-        std::io::stdin().read_line(&mut s).unwrap();
-        if s.trim().is_empty() {
-            break 'attendance 10;
+#[allow(clippy::too_many_arguments)]
+async fn book(
+    backend: &Backend,
+    profile: &Profile,
+    date: NaiveDate,
+    room: String,
+    from: NaiveTime,
+    to: NaiveTime,
+    group_size: u8,
+    confirmation: Option<String>,
+    rules: Option<PathBuf>,
+    format: Format,
+) {
+    if to <= from {
+        eprintln!("The booking must end after it starts, not at {to} when it starts at {from}");
+        std::process::exit(1);
+    }
+    let snapshots = require_snapshot_store("Recording bookings");
+    let Some(confirmation_id) = confirmation else {
+        // Read first, so that a missing credential is reported before the rooms are checked
+        let login = profile.credentials.as_ref().map(|credentials| {
+            let (card_number, _pin) = credentials.read().unwrap_or_else(|e| {
+                eprintln!("{e}");
+                std::process::exit(1);
+            });
+            let ending = card_number
+                .get(card_number.len().saturating_sub(4)..)
+                .unwrap_or(&card_number);
+            format!(
+                "Log in with the library card ending in {ending} and the PIN in ${},",
+                credentials.pin_env
+            )
+        });
+        let snapshot =
+            scrape_once(connect(backend).await, Some(&snapshots), date, group_size).await;
+        let (room, availability) = find_room(&snapshot.rooms, &room).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        });
+        if !availability.is_free_between(from, to) {
+            eprintln!(
+                "{} isn't free from {from} to {to} on {date}, only {availability}",
+                room.title()
+            );
+            std::process::exit(1);
         }
-        s.trim()
-            .parse::<u8>()
-            .expect("Failed to parse the number of attendees")
+        println!(
+            "{} is free from {from} to {to} on {date}. Book it at\n{}",
+            room.title(),
+            booking_url(date, group_size)
+        );
+        if let Some(login) = login {
+            println!("{login}");
+        }
+        println!("and record the booking with `--confirmation <ID>`.");
+        return;
     };
+    // Read first, so that nothing is recorded if the file is invalid
+    let mut webhooks = profile.webhooks.clone();
+    if let Some(path) = rules {
+        let WatchFile {
+            webhooks: watched, ..
+        } = read_toml_file(&path, "watch rules");
+        webhooks.extend(watched);
+    }
+    // The room was found when its availability was checked, so the recorded rooms are enough
+    let known_rooms = database_or_exit(snapshots.latest(date, Some(group_size)))
+        .map(|snapshot| snapshot.rooms)
+        .unwrap_or_default();
+    let (room, _) = find_room(&known_rooms, &room).unwrap_or_else(|e| {
+        eprintln!("{e} in the rooms checked for {date} and {group_size} people");
+        std::process::exit(1);
+    });
+    let booking = Booking {
+        confirmation_id,
+        room: room.clone(),
+        date,
+        from,
+        to,
+        group_size,
+    };
+    database_or_exit(snapshots.record_booking(&booking));
+    eprintln!("Recorded the booking:");
+    print_bookings(std::slice::from_ref(&booking), format);
 
-    let available_rooms: Vec<(Room, Availability)> =
-        c.available_rooms(day, attendance).await.unwrap();
-    for (room, availability) in available_rooms {
-        println!("{room:?}");
-        println!("{availability}");
+    // The booking is already printed, so only the webhooks are notified
+    let notification = Notification::BookingConfirmed(booking);
+    for webhook in webhooks.into_iter().map(WebhookNotifier::new) {
+        if let Err(e) = webhook.notify(&notification).await {
+            eprintln!("Failed to notify about the booking: {e}");
+        }
     }
+}
 
-    c.close().await.unwrap();
+fn bookings(all: bool, format: Format) {
+    let snapshots = require_snapshot_store("Listing bookings");
+    let since = (!all).then(library_today);
+    print_bookings(&database_or_exit(snapshots.bookings(since)), format);
+}
+
+fn cancel(confirmation: String) {
+    let snapshots = require_snapshot_store("Cancelling bookings");
+    match database_or_exit(snapshots.remove_booking(&confirmation)) {
+        Some(booking) => {
+            println!("Removed from the recorded bookings: {booking}");
+            println!("Unless it's already done, cancel it on the library's site as well.");
+        }
+        None => {
+            eprintln!("No booking with the confirmation id {confirmation:?} has been recorded");
+            std::process::exit(1);
+        }
+    }
+}
+
+// let's set up the sequence of steps we want the browser to take
+#[tokio::main]
+async fn main() {
+    let Cli {
+        format,
+        profile,
+        web_driver_host,
+        web_driver_port,
+        server,
+        command,
+    } = Cli::parse();
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    let profile = config.profile(profile.as_deref()).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    let backend = match server.or(config.server) {
+        Some(url) => Backend::Server(url),
+        None => Backend::WebDriver {
+            host: web_driver_host.unwrap_or(config.web_driver.host),
+            port: web_driver_port.unwrap_or(config.web_driver.port),
+        },
+    };
+    let (backend, profile) = (&backend, &profile);
+    match command {
+        None => search(backend, profile, None, None, format).await,
+        Some(Command::Search { date, group_size }) => {
+            search(backend, profile, date, group_size, format).await
+        }
+        Some(Command::Tui { date, group_size }) => {
+            let today = library_today();
+            let date = date.unwrap_or(today);
+            let group_size = group_size.unwrap_or(profile.group_size);
+            match tui::run(backend.clone(), profile.clone(), date, today, group_size).await {
+                Ok(Some(booking)) => println!("{booking}"),
+                Ok(None) => {}
+                Err(e) => eprintln!("The terminal UI failed: {e}"),
+            }
+        }
+        Some(Command::Scan {
+            dates,
+            from,
+            to,
+            group_size,
+        }) => {
+            let dates = match (dates, from, to) {
+                (Some(Dates(dates)), _, _) => dates,
+                (None, Some(from), Some(to)) if to < from => {
+                    eprintln!("{}", DateError::BackwardRange { from, to });
+                    std::process::exit(1);
+                }
+                (None, Some(from), Some(to)) => {
+                    from.iter_days().take_while(|date| *date <= to).collect()
+                }
+                _ => unreachable!("clap requires either --dates or --from and --to"),
+            };
+            let group_size = group_size.unwrap_or(profile.group_size);
+            scan(backend, profile, dates, group_size, format).await
+        }
+        Some(Command::Book {
+            date,
+            room,
+            from,
+            to,
+            group_size,
+            confirmation,
+            rules,
+        }) => {
+            book(
+                backend,
+                profile,
+                date,
+                room,
+                from,
+                to,
+                group_size.unwrap_or(profile.group_size),
+                confirmation,
+                rules,
+                format,
+            )
+            .await
+        }
+        Some(Command::Bookings { all }) => bookings(all, format),
+        Some(Command::Cancel { confirmation }) => cancel(confirmation),
+        Some(Command::Diff {
+            date,
+            group_size,
+            recorded,
+        }) => {
+            let group_size = group_size.unwrap_or(profile.group_size);
+            diff(backend, profile, date, group_size, recorded, format).await
+        }
+        Some(Command::Watch {
+            rules,
+            interval,
+            jitter,
+            max_backoff,
+        }) => {
+            let schedule = Schedule {
+                interval,
+                jitter,
+                max_backoff,
+            };
+            watch(backend, profile, rules, schedule).await
+        }
+        Some(Command::Digest { config, now }) => digest(backend, profile, config, now).await,
+    }
 }
@@ -0,0 +1,487 @@
+use std::fmt::Write;
+
+use calgary_central_library::{Availability, Booking, Calendar, Room, RoomChange, Snapshot};
+use chrono::{NaiveDate, NaiveTime};
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum Format {
+    /// Aligned columns with the title, floor, capacity and free time of every room.
+    Table,
+    /// A JSON array with an object per room.
+    Json,
+    /// A JSON object per room on every line.
+    Jsonl,
+    /// Comma-separated values with a header row.
+    Csv,
+    /// A Markdown table per day, e.g. for the meetup planning doc.
+    Markdown,
+    /// An iCalendar file with the free time of every room as tentative events.
+    Ics,
+}
+
+/// A room on a day, as it is printed.
+#[derive(Serialize)]
+struct RoomRow<'a> {
+    date: NaiveDate,
+    group_size: u8,
+    title: &'a str,
+    floor: Option<u8>,
+    capacity: Option<u8>,
+    /// The merged ranges of free time.
+    free: Vec<FreeRange>,
+}
+
+#[derive(Serialize)]
+struct FreeRange {
+    from: NaiveTime,
+    to: NaiveTime,
+}
+
+/// A room whose free time changed between two checks, as it is printed.
+#[derive(Serialize)]
+struct ChangeRow {
+    date: NaiveDate,
+    group_size: u8,
+    title: String,
+    floor: Option<u8>,
+    /// `appeared`, `disappeared` or `changed`.
+    change: &'static str,
+    /// The merged ranges of time that have become free.
+    opened: Vec<FreeRange>,
+    /// The merged ranges of free time that have been booked.
+    closed: Vec<FreeRange>,
+}
+
+#[derive(Serialize)]
+struct BookingRow<'a> {
+    confirmation_id: &'a str,
+    date: NaiveDate,
+    from: NaiveTime,
+    to: NaiveTime,
+    title: &'a str,
+    floor: Option<u8>,
+    group_size: u8,
+}
+
+fn time(time: NaiveTime) -> String {
+    time.format("%-I:%M %p").to_string()
+}
+
+fn optional(value: Option<u8>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn free_ranges(availability: &Availability) -> Vec<FreeRange> {
+    availability
+        .free_ranges()
+        .into_iter()
+        .map(|(from, to)| FreeRange { from, to })
+        .collect()
+}
+
+fn ranges(ranges: &[FreeRange]) -> String {
+    ranges
+        .iter()
+        .map(|range| format!("{}-{}", time(range.from), time(range.to)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl<'a> RoomRow<'a> {
+    fn rows(snapshot: &'a Snapshot) -> impl Iterator<Item = Self> + 'a {
+        snapshot.rooms.iter().map(|(room, availability)| Self {
+            date: snapshot.date,
+            group_size: snapshot.group_size,
+            title: room.title(),
+            floor: room.floor(),
+            capacity: room.capacity(),
+            free: free_ranges(availability),
+        })
+    }
+
+    fn free(&self) -> String {
+        if self.free.is_empty() {
+            return "booked".to_owned();
+        }
+        ranges(&self.free)
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.title.to_owned(),
+            optional(self.floor),
+            optional(self.capacity),
+            self.free(),
+        ]
+    }
+}
+
+impl ChangeRow {
+    fn rows(older: &Snapshot, newer: &Snapshot) -> Vec<Self> {
+        let diff = older.diff(newer);
+        let row = |room: &Room, change, opened, closed| Self {
+            date: newer.date,
+            group_size: newer.group_size,
+            title: room.title().to_owned(),
+            floor: room.floor(),
+            change,
+            opened,
+            closed,
+        };
+        let appeared = diff
+            .appeared
+            .iter()
+            .map(|(room, free)| row(room, "appeared", free_ranges(free), vec![]));
+        let disappeared = diff
+            .disappeared
+            .iter()
+            .map(|(room, free)| row(room, "disappeared", vec![], free_ranges(free)));
+        let changed = diff.changed.iter().map(|change| {
+            let RoomChange {
+                room,
+                opened,
+                closed,
+            } = change;
+            row(room, "changed", free_ranges(opened), free_ranges(closed))
+        });
+        appeared.chain(disappeared).chain(changed).collect()
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.title.clone(),
+            optional(self.floor),
+            self.change.to_owned(),
+            ranges(&self.opened),
+            ranges(&self.closed),
+        ]
+    }
+}
+
+impl<'a> BookingRow<'a> {
+    fn new(booking: &'a Booking) -> Self {
+        Self {
+            confirmation_id: &booking.confirmation_id,
+            date: booking.date,
+            from: booking.from,
+            to: booking.to,
+            title: booking.room.title(),
+            floor: booking.room.floor(),
+            group_size: booking.group_size,
+        }
+    }
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.date.to_string(),
+            time(self.from),
+            time(self.to),
+            self.title.to_owned(),
+            optional(self.floor),
+            self.group_size.to_string(),
+            self.confirmation_id.to_owned(),
+        ]
+    }
+}
+
+const ROOM_HEADER: [&str; 4] = ["Room", "Floor", "Capacity", "Free"];
+const CHANGE_HEADER: [&str; 5] = ["Room", "Floor", "Change", "Opened", "Closed"];
+const BOOKING_HEADER: [&str; 7] = [
+    "Date",
+    "From",
+    "To",
+    "Room",
+    "Floor",
+    "Group size",
+    "Confirmation",
+];
+
+/// Left-aligned columns separated by two spaces.
+fn table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    let header = header.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+    for row in std::iter::once(&header).chain(rows) {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(out, "{}", line.trim_end()).unwrap();
+    }
+    out
+}
+
+fn markdown_table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let escape = |cell: &str| cell.replace('|', "\\|");
+    let mut out = String::new();
+    writeln!(out, "| {} |", header.join(" | ")).unwrap();
+    writeln!(out, "|{}", "---|".repeat(header.len())).unwrap();
+    for row in rows {
+        let cells: Vec<_> = row.iter().map(|cell| escape(cell)).collect();
+        writeln!(out, "| {} |", cells.join(" | ")).unwrap();
+    }
+    out
+}
+
+fn csv_line(cells: &[String]) -> String {
+    cells
+        .iter()
+        .map(|cell| {
+            if cell.contains([',', '"', '\n']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn day_heading(snapshot: &Snapshot) -> String {
+    format!(
+        "{} ({} people)",
+        snapshot.date.format("%A, %B %-d, %Y"),
+        snapshot.group_size
+    )
+}
+
+pub(crate) fn rooms(snapshots: &[Snapshot], format: Format) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Table => {
+            for (i, snapshot) in snapshots.iter().enumerate() {
+                if i > 0 {
+                    writeln!(out).unwrap();
+                }
+                writeln!(out, "{}", day_heading(snapshot)).unwrap();
+                let rows: Vec<_> = RoomRow::rows(snapshot).map(|row| row.cells()).collect();
+                out.push_str(&table(&ROOM_HEADER, &rows));
+            }
+        }
+        Format::Markdown => {
+            for (i, snapshot) in snapshots.iter().enumerate() {
+                if i > 0 {
+                    writeln!(out).unwrap();
+                }
+                writeln!(out, "### {}\n", day_heading(snapshot)).unwrap();
+                let rows: Vec<_> = RoomRow::rows(snapshot).map(|row| row.cells()).collect();
+                out.push_str(&markdown_table(&ROOM_HEADER, &rows));
+            }
+        }
+        Format::Json => {
+            let rows: Vec<_> = snapshots.iter().flat_map(RoomRow::rows).collect();
+            writeln!(out, "{}", serde_json::to_string_pretty(&rows).unwrap()).unwrap();
+        }
+        Format::Jsonl => {
+            for row in snapshots.iter().flat_map(RoomRow::rows) {
+                writeln!(out, "{}", serde_json::to_string(&row).unwrap()).unwrap();
+            }
+        }
+        Format::Csv => {
+            let header = ["Date", "Group size", "Room", "Floor", "Capacity", "Free"];
+            writeln!(out, "{}", header.join(",")).unwrap();
+            for row in snapshots.iter().flat_map(RoomRow::rows) {
+                let mut cells = vec![row.date.to_string(), row.group_size.to_string()];
+                cells.extend(row.cells());
+                writeln!(out, "{}", csv_line(&cells)).unwrap();
+            }
+        }
+        Format::Ics => {
+            let Some(first) = snapshots.first() else {
+                return out;
+            };
+            let mut calendar = Calendar::new(format!("Free rooms for {} people", first.group_size));
+            for snapshot in snapshots {
+                calendar.add_free_windows(snapshot);
+            }
+            write!(out, "{calendar}").unwrap();
+        }
+    }
+    out
+}
+
+/// The rooms whose free time changed from the `older` check to the `newer` one of the same day.
+pub(crate) fn changes(older: &Snapshot, newer: &Snapshot, format: Format) -> String {
+    let rows = ChangeRow::rows(older, newer);
+    let cells: Vec<_> = rows.iter().map(ChangeRow::cells).collect();
+    let heading = format!(
+        "Changes between {} and {}",
+        older.scraped_at, newer.scraped_at
+    );
+    let mut out = String::new();
+    match format {
+        Format::Table | Format::Markdown if rows.is_empty() => {
+            writeln!(out, "{heading}: none").unwrap();
+        }
+        Format::Table => {
+            writeln!(out, "{heading}:").unwrap();
+            out.push_str(&table(&CHANGE_HEADER, &cells));
+        }
+        Format::Markdown => {
+            writeln!(out, "### {heading}\n").unwrap();
+            out.push_str(&markdown_table(&CHANGE_HEADER, &cells));
+        }
+        Format::Json => {
+            writeln!(out, "{}", serde_json::to_string_pretty(&rows).unwrap()).unwrap();
+        }
+        Format::Jsonl => {
+            for row in &rows {
+                writeln!(out, "{}", serde_json::to_string(row).unwrap()).unwrap();
+            }
+        }
+        Format::Csv => {
+            let header = [
+                "Date",
+                "Group size",
+                "Room",
+                "Floor",
+                "Change",
+                "Opened",
+                "Closed",
+            ];
+            writeln!(out, "{}", header.join(",")).unwrap();
+            for (row, row_cells) in rows.iter().zip(cells) {
+                let mut line = vec![row.date.to_string(), row.group_size.to_string()];
+                line.extend(row_cells);
+                writeln!(out, "{}", csv_line(&line)).unwrap();
+            }
+        }
+        Format::Ics => {
+            // The time that has become free, as the tentative events of `rooms`
+            let diff = older.diff(newer);
+            let opened = diff.appeared.into_iter().chain(
+                diff.changed
+                    .into_iter()
+                    .map(|change| (change.room, change.opened)),
+            );
+            let snapshot = Snapshot {
+                rooms: opened.collect(),
+                ..newer.clone()
+            };
+            let mut calendar =
+                Calendar::new(format!("Rooms that opened for {} people", newer.group_size));
+            calendar.add_free_windows(&snapshot);
+            write!(out, "{calendar}").unwrap();
+        }
+    }
+    out
+}
+
+pub(crate) fn bookings(bookings: &[Booking], format: Format) -> String {
+    let rows: Vec<_> = bookings.iter().map(BookingRow::new).collect();
+    let cells: Vec<_> = rows.iter().map(BookingRow::cells).collect();
+    let mut out = String::new();
+    match format {
+        Format::Table => out = table(&BOOKING_HEADER, &cells),
+        Format::Markdown => out = markdown_table(&BOOKING_HEADER, &cells),
+        Format::Json => {
+            writeln!(out, "{}", serde_json::to_string_pretty(&rows).unwrap()).unwrap();
+        }
+        Format::Jsonl => {
+            for row in &rows {
+                writeln!(out, "{}", serde_json::to_string(row).unwrap()).unwrap();
+            }
+        }
+        Format::Csv => {
+            writeln!(out, "{}", BOOKING_HEADER.join(",")).unwrap();
+            for row in &cells {
+                writeln!(out, "{}", csv_line(row)).unwrap();
+            }
+        }
+        Format::Ics => {
+            let mut calendar = Calendar::new("Room bookings");
+            for booking in bookings {
+                calendar.add_booking(booking);
+            }
+            write!(out, "{calendar}").unwrap();
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use calgary_central_library::fixtures;
+    use chrono::Utc;
+
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        let rooms = vec![
+            fixtures::free_room(
+                "3-20A Idea Lab",
+                &["6:00 PM", "6:30 PM", "7:00 PM", "8:00 PM"],
+            ),
+            (
+                fixtures::described_room("Room \"B\", west", ""),
+                fixtures::free(&[]),
+            ),
+        ];
+        Snapshot {
+            date: NaiveDate::from_ymd_opt(2026, 11, 12).unwrap(),
+            group_size: 20,
+            scraped_at: Utc::now(),
+            rooms,
+        }
+    }
+
+    #[test]
+    fn table_merges_free_ranges() {
+        let out = rooms(&[snapshot()], Format::Table);
+        assert_eq!(
+            out,
+            "Thursday, November 12, 2026 (20 people)\n\
+             Room            Floor  Capacity  Free\n\
+             3-20A Idea Lab  3      24        6:00 PM-7:30 PM, 8:00 PM-8:30 PM\n\
+             Room \"B\", west                   booked\n"
+        );
+    }
+
+    #[test]
+    fn markdown_has_a_table_per_day() {
+        let out = rooms(&[snapshot()], Format::Markdown);
+        assert!(out.starts_with("### Thursday, November 12, 2026 (20 people)\n\n| Room |"));
+        assert!(out.contains("| 3-20A Idea Lab | 3 | 24 | 6:00 PM-7:30 PM, 8:00 PM-8:30 PM |\n"));
+    }
+
+    #[test]
+    fn csv_quotes_cells() {
+        let out = rooms(&[snapshot()], Format::Csv);
+        assert!(out.contains("\n2026-11-12,20,\"Room \"\"B\"\", west\",,,booked\n"));
+    }
+
+    #[test]
+    fn changes_list_opened_and_closed_time() {
+        let older = snapshot();
+        let mut newer = snapshot();
+        newer.rooms[0].1 = fixtures::free(&["6:00 PM", "6:30 PM", "7:00 PM", "7:30 PM"]);
+        newer.rooms.pop();
+        let out = changes(&older, &newer, Format::Csv);
+        assert_eq!(
+            out.lines().skip(1).collect::<Vec<_>>(),
+            [
+                "2026-11-12,20,\"Room \"\"B\"\", west\",,disappeared,,",
+                "2026-11-12,20,3-20A Idea Lab,3,changed,7:30 PM-8:00 PM,8:00 PM-8:30 PM",
+            ]
+        );
+        assert!(changes(&older, &older, Format::Table).ends_with(": none\n"));
+    }
+
+    #[test]
+    fn jsonl_has_an_object_per_room() {
+        let out = rooms(&[snapshot()], Format::Jsonl);
+        let first: serde_json::Value = serde_json::from_str(out.lines().next().unwrap()).unwrap();
+        assert_eq!(first["floor"], 3);
+        assert_eq!(first["free"][0]["to"], "19:30:00");
+        assert_eq!(out.lines().count(), 2);
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt;
+
+use calgary_central_library::{
+    AsyncClient, Client, RemoteClient, RemoteError, Snapshot, SnapshotStore, SqliteSnapshotStore,
+};
+use chrono::{NaiveDate, Utc};
+use thiserror::Error;
+
+/// Where the rooms are checked: in a WebDriver session of the app's own
+/// or by a running crawling server (`ccl_headless_browser_server`).
+#[derive(Clone)]
+pub(crate) enum Backend {
+    WebDriver {
+        host: String,
+        port: u16,
+    },
+    /// The URL of the crawling server, e.g. `http://localhost:3000`.
+    Server(String),
+}
+
+/// A connected [`Backend`].
+pub(crate) enum Source {
+    WebDriver(Client),
+    Server(RemoteClient),
+}
+
+/// A [`Source`] that is connected when it's first needed and connected again after a failed check,
+/// e.g. once the WebDriver session was lost because the WebDriver server restarted.
+pub(crate) struct Reconnecting {
+    backend: Backend,
+    source: Option<Source>,
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum ConnectError {
+    #[error("Failed to connect to WebDriver: {0}")]
+    WebDriver(<Client as AsyncClient>::NewError),
+    #[error(transparent)]
+    Server(#[from] RemoteError),
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum ScrapeError {
+    #[error(transparent)]
+    WebDriver(<Client as AsyncClient>::AvailableRoomsError),
+    #[error(transparent)]
+    Server(#[from] RemoteError),
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum CheckError {
+    #[error(transparent)]
+    Connect(#[from] ConnectError),
+    #[error(transparent)]
+    Scrape(#[from] ScrapeError),
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::WebDriver { host, port } => write!(f, "WebDriver at http://{host}:{port}"),
+            Backend::Server(url) => write!(f, "the crawling server at {url}"),
+        }
+    }
+}
+
+impl Backend {
+    pub(crate) async fn connect(&self) -> Result<Source, ConnectError> {
+        eprintln!("Connecting to {self}...");
+        self.connect_quietly().await
+    }
+
+    /// Like [`Backend::connect`], without printing anything, e.g. while the terminal UI is shown.
+    pub(crate) async fn connect_quietly(&self) -> Result<Source, ConnectError> {
+        match self {
+            Backend::WebDriver { host, port } => Client::new((host.as_str(), *port))
+                .await
+                .map(Source::WebDriver)
+                .map_err(ConnectError::WebDriver),
+            Backend::Server(url) => {
+                let client = RemoteClient::new(url.as_str());
+                client.check_health().await?;
+                Ok(Source::Server(client))
+            }
+        }
+    }
+}
+
+impl Source {
+    /// Checks the rooms and records them if `snapshots` is available.
+    pub(crate) async fn scrape(
+        &self,
+        snapshots: Option<&SqliteSnapshotStore>,
+        date: NaiveDate,
+        group_size: u8,
+    ) -> Result<Snapshot, ScrapeError> {
+        let snapshot = match self {
+            Source::WebDriver(c) => Snapshot {
+                date,
+                group_size,
+                scraped_at: Utc::now(),
+                rooms: c
+                    .available_rooms(date, group_size)
+                    .await
+                    .map_err(ScrapeError::WebDriver)?,
+            },
+            Source::Server(client) => client.available_rooms(date, group_size).await?,
+        };
+        if let Some(store) = snapshots {
+            if let Err(e) = store.save(&snapshot) {
+                eprintln!("Failed to record the rooms: {e}");
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Ends the WebDriver session, if any.
+    pub(crate) async fn close(self) {
+        if let Source::WebDriver(c) = self {
+            if let Err(e) = c.close().await {
+                eprintln!("Failed to close the WebDriver session: {e}");
+            }
+        }
+    }
+}
+
+impl Reconnecting {
+    pub(crate) fn new(backend: Backend) -> Self {
+        Self {
+            backend,
+            source: None,
+        }
+    }
+
+    /// Like [`Source::scrape`]. The source is dropped if the check fails, the same way
+    /// the crawling server recycles a session, so that the next check starts with a new one.
+    pub(crate) async fn scrape(
+        &mut self,
+        snapshots: Option<&SqliteSnapshotStore>,
+        date: NaiveDate,
+        group_size: u8,
+    ) -> Result<Snapshot, CheckError> {
+        let source = match &mut self.source {
+            Some(source) => source,
+            None => self.source.insert(self.backend.connect_quietly().await?),
+        };
+        let result = source.scrape(snapshots, date, group_size).await;
+        if result.is_err() {
+            if let Some(Source::WebDriver(c)) = self.source.take() {
+                // The session is most likely gone already
+                let _ = c.close().await;
+            }
+        }
+        Ok(result?)
+    }
+
+    pub(crate) async fn close(self) {
+        if let Some(source) = self.source {
+            source.close().await;
+        }
+    }
+}
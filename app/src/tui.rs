@@ -0,0 +1,560 @@
+use std::{collections::HashMap, fmt, time::Duration};
+
+use calgary_central_library::{booking_url, Availability, Profile, Room, Snapshot, SnapshotStore};
+use chrono::{Days, NaiveDate, NaiveTime, Timelike};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Cell, Paragraph, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+use tokio::{
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
+    task::JoinHandle,
+};
+
+use crate::{
+    open_snapshot_store,
+    source::{Backend, Reconnecting},
+};
+
+/// The grid shows the half-hour slots from 8 AM to 10 PM.
+const FIRST_SLOT: u32 = 8 * 2;
+const SLOTS: usize = 28;
+/// The minimum capacities that `c` cycles through.
+const CAPACITIES: [Option<u8>; 5] = [None, Some(10), Some(20), Some(30), Some(50)];
+
+fn slot_time(slot: usize) -> NaiveTime {
+    let half_hours = FIRST_SLOT + slot as u32;
+    NaiveTime::from_hms_opt(half_hours / 2, half_hours % 2 * 30, 0).unwrap()
+}
+
+fn slot_end(slot: usize) -> NaiveTime {
+    slot_time(slot) + chrono::Duration::try_minutes(30).unwrap()
+}
+
+/// The rooms of a day, possibly from an earlier check while they are checked again.
+#[derive(Default)]
+struct Day {
+    snapshot: Option<Snapshot>,
+    loading: bool,
+    error: Option<String>,
+}
+
+/// A booking that was started from the grid, to be finished on the library's site.
+#[derive(Debug, PartialEq)]
+pub(crate) struct PendingBooking {
+    date: NaiveDate,
+    room: String,
+    from: NaiveTime,
+    to: NaiveTime,
+    group_size: u8,
+}
+
+impl fmt::Display for PendingBooking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Book {} on {} from {} to {} at",
+            self.room,
+            self.date,
+            self.from.format("%-I:%M %p"),
+            self.to.format("%-I:%M %p"),
+        )?;
+        writeln!(f, "{}", booking_url(self.date, self.group_size))?;
+        writeln!(f, "and record the booking with")?;
+        write!(
+            f,
+            "app book --date {} --room {:?} --from {} --to {} --group-size {} --confirmation <ID>",
+            self.date,
+            self.room,
+            self.from.format("%H:%M"),
+            self.to.format("%H:%M"),
+            self.group_size,
+        )
+    }
+}
+
+enum Action {
+    None,
+    Quit,
+    Refresh(NaiveDate),
+}
+
+struct App {
+    date: NaiveDate,
+    today: NaiveDate,
+    group_size: u8,
+    days: HashMap<NaiveDate, Day>,
+    table: TableState,
+    slot: usize,
+    min_capacity: usize,
+    /// Only the rooms whose title or description mention it are shown, e.g. `projector`.
+    amenity: String,
+    /// The amenity that is being typed.
+    editing: Option<String>,
+    booking: Option<PendingBooking>,
+}
+
+impl App {
+    fn new(date: NaiveDate, today: NaiveDate, group_size: u8) -> Self {
+        Self {
+            date,
+            today,
+            group_size,
+            days: HashMap::new(),
+            table: TableState::default().with_selected(0),
+            slot: 0,
+            min_capacity: 0,
+            amenity: String::new(),
+            editing: None,
+            booking: None,
+        }
+    }
+
+    fn day(&self) -> Option<&Day> {
+        self.days.get(&self.date)
+    }
+
+    fn visible_rooms(&self) -> Vec<&(Room, Availability)> {
+        let Some(snapshot) = self.day().and_then(|day| day.snapshot.as_ref()) else {
+            return Vec::new();
+        };
+        let amenity = self.amenity.to_lowercase();
+        snapshot
+            .rooms
+            .iter()
+            .filter(|(room, _)| match CAPACITIES[self.min_capacity] {
+                Some(min) => room.capacity().is_some_and(|capacity| capacity >= min),
+                None => true,
+            })
+            .filter(|(room, _)| {
+                amenity.is_empty()
+                    || room.title().to_lowercase().contains(&amenity)
+                    || room.description().to_lowercase().contains(&amenity)
+            })
+            .collect()
+    }
+
+    /// Shows `date`, and checks its rooms unless they are known or being checked.
+    fn go_to(&mut self, date: NaiveDate) -> Action {
+        self.date = date;
+        self.table.select(Some(0));
+        let day = self.days.entry(date).or_default();
+        if day.snapshot.is_some() || day.loading {
+            return Action::None;
+        }
+        self.refresh()
+    }
+
+    fn refresh(&mut self) -> Action {
+        let day = self.days.entry(self.date).or_default();
+        if day.loading {
+            return Action::None;
+        }
+        day.loading = true;
+        day.error = None;
+        Action::Refresh(self.date)
+    }
+
+    fn received(&mut self, date: NaiveDate, result: Result<Snapshot, String>) {
+        let day = self.days.entry(date).or_default();
+        day.loading = false;
+        match result {
+            Ok(snapshot) => day.snapshot = Some(snapshot),
+            Err(e) => day.error = Some(e),
+        }
+    }
+
+    /// Starts booking the highlighted slot, extended to the whole free range that contains it.
+    fn start_booking(&mut self) {
+        let rooms = self.visible_rooms();
+        let Some((room, availability)) = self.table.selected().and_then(|row| rooms.get(row))
+        else {
+            return;
+        };
+        let (from, to) = (slot_time(self.slot), slot_end(self.slot));
+        let range = availability
+            .free_ranges()
+            .into_iter()
+            .find(|(start, end)| *start <= from && to <= *end);
+        let booking = range.map(|(from, to)| PendingBooking {
+            date: self.date,
+            room: room.title().to_owned(),
+            from,
+            to,
+            group_size: self.group_size,
+        });
+        self.booking = booking;
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if let Some(editing) = &mut self.editing {
+            match key.code {
+                KeyCode::Enter => {
+                    self.amenity = self.editing.take().unwrap();
+                    self.table.select(Some(0));
+                }
+                KeyCode::Esc => self.editing = None,
+                KeyCode::Backspace => {
+                    editing.pop();
+                }
+                KeyCode::Char(c) => editing.push(c),
+                _ => {}
+            }
+            return Action::None;
+        }
+        let rooms = self.visible_rooms().len();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => {
+                let row = self.table.selected().map_or(0, |row| row + 1);
+                self.table.select(Some(row.min(rooms.saturating_sub(1))));
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.slot = self.slot.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') => self.slot = (self.slot + 1).min(SLOTS - 1),
+            KeyCode::Char('n') | KeyCode::PageDown => {
+                return self.go_to(self.date + Days::new(1));
+            }
+            KeyCode::Char('p') | KeyCode::PageUp if self.date > self.today => {
+                return self.go_to(self.date - Days::new(1));
+            }
+            KeyCode::Char('r') => return self.refresh(),
+            KeyCode::Char('c') => {
+                self.min_capacity = (self.min_capacity + 1) % CAPACITIES.len();
+                self.table.select(Some(0));
+            }
+            KeyCode::Char('/') => self.editing = Some(self.amenity.clone()),
+            KeyCode::Char('b') | KeyCode::Enter => self.start_booking(),
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn status(&self) -> String {
+        let mut status = format!(
+            "{} | {} people",
+            self.date.format("%a %b %-d, %Y"),
+            self.group_size
+        );
+        if let Some(min) = CAPACITIES[self.min_capacity] {
+            status.push_str(&format!(" | capacity {min}+"));
+        }
+        if !self.amenity.is_empty() {
+            status.push_str(&format!(" | mentions {:?}", self.amenity));
+        }
+        match self.day() {
+            Some(Day { loading: true, .. }) => status.push_str(" | checking..."),
+            Some(Day { error: Some(e), .. }) => {
+                status.push_str(&format!(" | failed to check: {e}"))
+            }
+            Some(Day {
+                snapshot: Some(snapshot),
+                ..
+            }) => status.push_str(&format!(
+                " | checked at {}",
+                snapshot
+                    .scraped_at
+                    .with_timezone(&chrono::Local)
+                    .format("%-I:%M %p")
+            )),
+            _ => {}
+        }
+        status
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [top, grid, bottom] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        frame.render_widget(Paragraph::new(self.status()), top);
+
+        let header = std::iter::once(Cell::from("Room"))
+            .chain(std::iter::once(Cell::from("Cap")))
+            .chain((0..SLOTS).map(|slot| {
+                let time = slot_time(slot);
+                match time.minute() {
+                    0 => Cell::from(time.hour().to_string()),
+                    _ => Cell::from(""),
+                }
+            }));
+        let selected = self.table.selected();
+        let rows: Vec<Row> = self
+            .visible_rooms()
+            .into_iter()
+            .enumerate()
+            .map(|(row, (room, availability))| {
+                let slots = (0..SLOTS).map(|slot| {
+                    let free = availability.is_free_between(slot_time(slot), slot_end(slot));
+                    let style = match (free, selected == Some(row) && slot == self.slot) {
+                        (_, true) => Style::new().bg(Color::Yellow).fg(Color::Black),
+                        (true, false) => Style::new().bg(Color::Green),
+                        (false, false) => Style::new().fg(Color::DarkGray),
+                    };
+                    Cell::from(if free { "  " } else { "··" }).style(style)
+                });
+                let capacity = room.capacity().map(|c| c.to_string()).unwrap_or_default();
+                Row::new(
+                    [Cell::from(room.title().to_owned()), Cell::from(capacity)]
+                        .into_iter()
+                        .chain(slots),
+                )
+            })
+            .collect();
+        let widths = [Constraint::Length(28), Constraint::Length(4)]
+            .into_iter()
+            .chain(std::iter::repeat_n(Constraint::Length(2), SLOTS));
+        let table = Table::new(rows, widths)
+            .header(Row::new(header).style(Style::new().add_modifier(Modifier::BOLD)))
+            .column_spacing(0)
+            .row_highlight_style(Style::new().add_modifier(Modifier::BOLD));
+        frame.render_stateful_widget(table, grid, &mut self.table);
+
+        let help = match (&self.editing, &self.booking) {
+            (Some(editing), _) => format!("Mentions: {editing}_ (Enter to filter, Esc to cancel)"),
+            (None, Some(booking)) => format!(
+                "Booking {} {}-{}, shown again on exit | q quit",
+                booking.room,
+                booking.from.format("%-I:%M %p"),
+                booking.to.format("%-I:%M %p"),
+            ),
+            (None, None) => format!(
+                "arrows move | n/p next/previous day | r refresh | c capacity | / amenity | b book {} | q quit",
+                slot_time(self.slot).format("%-I:%M %p")
+            ),
+        };
+        frame.render_widget(Paragraph::new(Line::from(help)), bottom);
+    }
+}
+
+/// The rooms of a day or why they couldn't be checked.
+type Checked = (NaiveDate, Result<Snapshot, String>);
+
+/// Checks the requested dates one at a time with a single WebDriver session,
+/// which is opened again after a failed check. Sending to the returned [`oneshot::Sender`]
+/// stops the worker without checking the dates that are still queued.
+fn spawn_scraper(
+    backend: Backend,
+    profile: Profile,
+    group_size: u8,
+) -> (
+    UnboundedSender<NaiveDate>,
+    UnboundedReceiver<Checked>,
+    oneshot::Sender<()>,
+    JoinHandle<()>,
+) {
+    let (requests, mut requested) = mpsc::unbounded_channel();
+    let (results, received) = mpsc::unbounded_channel();
+    let (stop, mut stopped) = oneshot::channel();
+    let worker = tokio::spawn(async move {
+        let snapshots = open_snapshot_store();
+        let mut source = Reconnecting::new(backend);
+        loop {
+            let date = tokio::select! {
+                _ = &mut stopped => break,
+                date = requested.recv() => match date {
+                    Some(date) => date,
+                    None => break,
+                },
+            };
+            let result = tokio::select! {
+                _ = &mut stopped => break,
+                result = source.scrape(snapshots.as_ref(), date, group_size) => result,
+            };
+            let result = result
+                .map(|mut snapshot| {
+                    profile.rank(&mut snapshot.rooms);
+                    snapshot
+                })
+                .map_err(|e| format!("{e}"));
+            let _ = results.send((date, result));
+        }
+        source.close().await;
+    });
+    (requests, received, stop, worker)
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    requests: &UnboundedSender<NaiveDate>,
+    results: &mut UnboundedReceiver<Checked>,
+) -> std::io::Result<()> {
+    loop {
+        while let Ok((date, result)) = results.try_recv() {
+            app.received(date, result);
+        }
+        terminal.draw(|frame| app.draw(frame))?;
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.handle_key(key) {
+            Action::None => {}
+            Action::Quit => return Ok(()),
+            Action::Refresh(date) => {
+                let _ = requests.send(date);
+            }
+        }
+    }
+}
+
+/// Shows the rooms as a grid of half-hour slots until it's closed, starting on `date`.
+/// Returns the booking that was started, if any.
+pub(crate) async fn run(
+    backend: Backend,
+    profile: Profile,
+    date: NaiveDate,
+    today: NaiveDate,
+    group_size: u8,
+) -> std::io::Result<Option<PendingBooking>> {
+    let mut app = App::new(date, today, group_size);
+    // The last known rooms are shown until they are checked again
+    if let Some(store) = open_snapshot_store() {
+        if let Ok(Some(mut snapshot)) = store.latest(date, Some(group_size)) {
+            profile.rank(&mut snapshot.rooms);
+            app.days.entry(date).or_default().snapshot = Some(snapshot);
+        }
+    }
+    let (requests, mut results, stop, worker) = spawn_scraper(backend, profile, group_size);
+    if let Action::Refresh(date) = app.refresh() {
+        let _ = requests.send(date);
+    }
+
+    let mut terminal = ratatui::init();
+    let result = tokio::task::block_in_place(|| {
+        event_loop(&mut terminal, &mut app, &requests, &mut results)
+    });
+    ratatui::restore();
+
+    // The queued dates aren't checked anymore, only the session is closed
+    let _ = stop.send(());
+    let _ = worker.await;
+    result.map(|()| app.booking)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use ratatui::crossterm::event::KeyModifiers;
+
+    use super::*;
+    use calgary_central_library::fixtures::{described_room, free};
+
+    fn thursday() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 11, 12).unwrap()
+    }
+
+    fn app() -> App {
+        let rooms = vec![
+            (
+                described_room(
+                    "3-20A Idea Lab",
+                    "It has a projector and can accommodate up to 24 people",
+                ),
+                free(&["6:00 PM", "6:30 PM", "7:00 PM"]),
+            ),
+            (
+                described_room("3-10A Meeting Room", "It can accommodate up to 12 people"),
+                free(&[]),
+            ),
+        ];
+        let mut app = App::new(thursday(), thursday(), 10);
+        app.received(
+            thursday(),
+            Ok(Snapshot {
+                date: thursday(),
+                group_size: 10,
+                scraped_at: Utc::now(),
+                rooms,
+            }),
+        );
+        app
+    }
+
+    fn press(app: &mut App, code: KeyCode) -> Action {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn titles(app: &App) -> Vec<&str> {
+        app.visible_rooms()
+            .into_iter()
+            .map(|(room, _)| room.title())
+            .collect()
+    }
+
+    #[test]
+    fn rooms_are_filtered_by_capacity_and_amenity() {
+        let mut app = app();
+        press(&mut app, KeyCode::Char('c'));
+        press(&mut app, KeyCode::Char('c'));
+        assert_eq!(titles(&app), ["3-20A Idea Lab"]);
+
+        press(&mut app, KeyCode::Char('c'));
+        press(&mut app, KeyCode::Char('c'));
+        press(&mut app, KeyCode::Char('c'));
+        assert_eq!(titles(&app).len(), 2);
+
+        for code in [KeyCode::Char('/'), KeyCode::Char('P'), KeyCode::Char('r')] {
+            press(&mut app, code);
+        }
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(titles(&app), ["3-20A Idea Lab"]);
+    }
+
+    #[test]
+    fn other_days_are_checked_once() {
+        let mut app = app();
+        assert!(matches!(press(&mut app, KeyCode::Char('p')), Action::None));
+        assert_eq!(app.date, thursday());
+
+        let friday = thursday().succ_opt().unwrap();
+        assert!(
+            matches!(press(&mut app, KeyCode::Char('n')), Action::Refresh(date) if date == friday)
+        );
+        assert!(matches!(press(&mut app, KeyCode::Char('r')), Action::None));
+        assert!(matches!(press(&mut app, KeyCode::Char('p')), Action::None));
+        assert!(matches!(press(&mut app, KeyCode::Char('n')), Action::None));
+    }
+
+    #[test]
+    fn booking_covers_the_free_range_of_the_slot() {
+        let mut app = app();
+        // 6:30 PM
+        for _ in 0..21 {
+            press(&mut app, KeyCode::Right);
+        }
+        press(&mut app, KeyCode::Char('b'));
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert_eq!(
+            app.booking,
+            Some(PendingBooking {
+                date: thursday(),
+                room: "3-20A Idea Lab".to_owned(),
+                from: time(18, 0),
+                to: time(19, 30),
+                group_size: 10,
+            })
+        );
+
+        // The other room is booked all day
+        app.booking = None;
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char('b'));
+        assert_eq!(app.booking, None);
+    }
+}
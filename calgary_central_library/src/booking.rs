@@ -0,0 +1,32 @@
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::room::Room;
+
+/// A reservation of a room.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Booking {
+    /// The identifier that the library gave to the reservation.
+    pub confirmation_id: String,
+    pub room: Room,
+    pub date: NaiveDate,
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+    pub group_size: u8,
+}
+
+impl std::fmt::Display for Booking {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is booked on {} from {} to {} for {} people (confirmation {})",
+            self.room.title(),
+            self.date,
+            self.from.format("%-I:%M %p"),
+            self.to.format("%-I:%M %p"),
+            self.group_size,
+            self.confirmation_id,
+        )
+    }
+}
@@ -0,0 +1,360 @@
+//! The configuration that the command-line app, the crawling server and the desktop app share,
+//! so that an organizer sets up their WebDriver server and their preferences only once.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[cfg(feature = "email")]
+use crate::email::SmtpConfig;
+#[cfg(feature = "webhook")]
+use crate::webhook::WebhookConfig;
+use crate::{
+    room::{Availability, Room},
+    watch::TimeWindow,
+};
+
+/// The `location` identifier of the Central Library, the only branch whose rooms can be checked so far.
+const CENTRAL_LIBRARY_BRANCH_ID: u32 = 1;
+
+/// The contents of [`Config::path`]. Every key is optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub web_driver: WebDriverEndpoint,
+    /// The URL of a running crawling server, e.g. `http://localhost:3000`, that the front ends
+    /// use instead of a WebDriver server of their own.
+    pub server: Option<String>,
+    /// The name of the profile that is used unless another one is asked for.
+    pub default_profile: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// The `host:port` address of a WebDriver server, e.g. chromedriver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebDriverEndpoint {
+    pub host: String,
+    pub port: u16,
+}
+
+/// The preferences of an organizer or of a group.
+///
+/// Unknown keys are ignored rather than rejected, so that a front end that is built without
+/// the notifiers can read the same file as one that has them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// The expected number of attendees, unless another one is given.
+    pub group_size: u8,
+    /// The `location` identifiers of the acceptable branches.
+    pub branches: Vec<u32>,
+    /// The prefixes of the titles of the preferred rooms, e.g. `3-20A`. They are listed first.
+    pub rooms: Vec<String>,
+    /// The times of day for which a room should be free. The rooms that are free for one of them
+    /// are listed before the others.
+    pub time_windows: Vec<TimeWindow>,
+    pub credentials: Option<Credentials>,
+    /// The webhooks that receive the notifications of the profile.
+    #[cfg(feature = "webhook")]
+    pub webhooks: Vec<WebhookConfig>,
+    /// The server that sends the emails of the profile, e.g. the digests.
+    #[cfg(feature = "email")]
+    pub smtp: Option<SmtpConfig>,
+}
+
+/// Where the library card number and the PIN that log in to the library's site are found.
+/// Only the names of the environment variables are stored, never the secrets themselves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Credentials {
+    pub card_number_env: String,
+    pub pin_env: String,
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read the configuration file {path:?}: {source}")]
+    ReadFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse the configuration file {path:?}: {source}")]
+    ParseFile {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("No profile is named {0:?}")]
+    UnknownProfile(String),
+    #[error("The profile {profile:?} is invalid: {reason}")]
+    InvalidProfile {
+        profile: String,
+        reason: &'static str,
+    },
+    #[error("The environment variable {0} with a library credential is not set")]
+    MissingCredential(String),
+}
+
+#[derive(Error, Debug)]
+#[error("expected a `host:port` address, got {0:?}")]
+pub struct ParseWebDriverEndpointError(String);
+
+impl Default for WebDriverEndpoint {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_owned(),
+            port: 4444,
+        }
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            group_size: 10,
+            branches: vec![CENTRAL_LIBRARY_BRANCH_ID],
+            rooms: Vec::new(),
+            time_windows: Vec::new(),
+            credentials: None,
+            #[cfg(feature = "webhook")]
+            webhooks: Vec::new(),
+            #[cfg(feature = "email")]
+            smtp: None,
+        }
+    }
+}
+
+impl Config {
+    /// `config.toml` in the platform's configuration directory,
+    /// e.g. `~/.config/booking-rooms/config.toml` on Linux.
+    pub fn path() -> Option<PathBuf> {
+        Some(crate::project_dirs()?.config_dir().join("config.toml"))
+    }
+
+    /// Loads the configuration from [`Config::path`]. Without the file, everything is the default.
+    pub fn load() -> Result<Self, ConfigError> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let file = std::fs::read_to_string(path).map_err(|source| ConfigError::ReadFile {
+            path: path.to_owned(),
+            source,
+        })?;
+        let config: Self = toml::from_str(&file).map_err(|source| ConfigError::ParseFile {
+            path: path.to_owned(),
+            source,
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(name) = &self.default_profile {
+            if !self.profiles.contains_key(name) {
+                return Err(ConfigError::UnknownProfile(name.clone()));
+            }
+        }
+        for (name, profile) in &self.profiles {
+            let invalid = |reason| ConfigError::InvalidProfile {
+                profile: name.clone(),
+                reason,
+            };
+            if profile.group_size == 0 {
+                return Err(invalid("`group_size` must be greater than zero"));
+            }
+            if profile.branches.is_empty() {
+                return Err(invalid("at least one branch must be acceptable"));
+            }
+        }
+        Ok(())
+    }
+
+    /// The profile named `name`, or the default one if `name` is `None`.
+    /// Without a default profile, the defaults of [`Profile`] are used.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, ConfigError> {
+        match name.or(self.default_profile.as_deref()) {
+            Some(name) => self
+                .profiles
+                .get(name)
+                .cloned()
+                .ok_or_else(|| ConfigError::UnknownProfile(name.to_owned())),
+            None => Ok(Profile::default()),
+        }
+    }
+}
+
+impl Profile {
+    /// Whether the title of the room starts with one of the preferred prefixes.
+    pub fn prefers(&self, room: &Room) -> bool {
+        self.rooms
+            .iter()
+            .any(|prefix| room.title().starts_with(prefix.as_str()))
+    }
+
+    /// Whether the room is free for one of the time windows of the profile.
+    pub fn fits(&self, availability: &Availability) -> bool {
+        self.time_windows
+            .iter()
+            .any(|window| availability.is_free_between(window.from, window.to))
+    }
+
+    /// Moves the preferred rooms first, then the ones that fit a time window,
+    /// keeping the order of the library's site otherwise.
+    pub fn rank(&self, rooms: &mut [(Room, Availability)]) {
+        rooms.sort_by_key(|(room, availability)| (!self.prefers(room), !self.fits(availability)));
+    }
+}
+
+impl Credentials {
+    /// The library card number and the PIN.
+    pub fn read(&self) -> Result<(String, String), ConfigError> {
+        let read = |name: &str| {
+            std::env::var(name).map_err(|_| ConfigError::MissingCredential(name.to_owned()))
+        };
+        Ok((read(&self.card_number_env)?, read(&self.pin_env)?))
+    }
+}
+
+impl FromStr for WebDriverEndpoint {
+    type Err = ParseWebDriverEndpointError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseWebDriverEndpointError(s.to_owned());
+        let (host, port) = s.trim().rsplit_once(':').ok_or_else(err)?;
+        if host.is_empty() {
+            return Err(err());
+        }
+        Ok(Self {
+            host: host.to_owned(),
+            port: port.parse().map_err(|_| err())?,
+        })
+    }
+}
+
+impl fmt::Display for WebDriverEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+impl Serialize for WebDriverEndpoint {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for WebDriverEndpoint {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room::fixtures::free_room;
+
+    fn parse(file: &str) -> Result<Config, ConfigError> {
+        let config: Config = toml::from_str(file).map_err(|source| ConfigError::ParseFile {
+            path: PathBuf::new(),
+            source,
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[test]
+    fn example_config_is_valid() {
+        let config = parse(include_str!("../config.example.toml")).unwrap();
+        assert_eq!(config.web_driver.to_string(), "localhost:4444");
+        let profile = config.profile(None).unwrap();
+        assert_eq!(profile.group_size, 20);
+        assert_eq!(config.profile(Some("workshops")).unwrap().group_size, 10);
+    }
+
+    #[test]
+    fn profiles_default_without_a_file() {
+        let config = parse("").unwrap();
+        assert_eq!(config.web_driver, WebDriverEndpoint::default());
+        assert_eq!(config.profile(None).unwrap().branches, [1]);
+        assert!(matches!(
+            config.profile(Some("meetups")),
+            Err(ConfigError::UnknownProfile(name)) if name == "meetups"
+        ));
+    }
+
+    #[test]
+    fn invalid_profiles_are_rejected() {
+        let err = parse("default_profile = \"meetups\"").unwrap_err();
+        assert!(matches!(err, ConfigError::UnknownProfile(_)));
+        let file = r#"
+            [profiles.meetups]
+            group_size = 0
+        "#;
+        let err = parse(file).unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidProfile { profile, .. } if profile == "meetups"));
+        let err = parse("profiles.meetups.branches = []").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidProfile { .. }));
+        // Time windows are checked when they are read
+        let file = r#"
+            [profiles.meetups]
+            time_windows = [{ from = "21:00", to = "18:00" }]
+        "#;
+        assert!(matches!(parse(file), Err(ConfigError::ParseFile { .. })));
+    }
+
+    #[test]
+    fn web_driver_endpoint_needs_a_port() {
+        assert!("localhost".parse::<WebDriverEndpoint>().is_err());
+        assert!(":4444".parse::<WebDriverEndpoint>().is_err());
+        assert_eq!(
+            "localhost:4444".parse::<WebDriverEndpoint>().unwrap().port,
+            4444
+        );
+    }
+
+    #[test]
+    fn missing_credentials_are_named() {
+        let credentials = Credentials {
+            card_number_env: "BOOKING_ROOMS_TEST_NO_SUCH_CARD".to_owned(),
+            pin_env: "BOOKING_ROOMS_TEST_NO_SUCH_PIN".to_owned(),
+        };
+        assert!(matches!(
+            credentials.read(),
+            Err(ConfigError::MissingCredential(name)) if name == "BOOKING_ROOMS_TEST_NO_SUCH_CARD"
+        ));
+    }
+
+    #[test]
+    fn preferred_rooms_come_first() {
+        let rooms = [
+            free_room("3-10A Meeting Room", &["6:00 AM", "6:30 AM"]),
+            free_room("2-05 Study Room", &["6:00 PM", "6:30 PM"]),
+            free_room("3-20A Idea Lab", &["6:00 AM"]),
+        ];
+        let profile: Profile = toml::from_str(
+            r#"
+                rooms = ["3-20A"]
+                time_windows = [{ from = "18:00", to = "19:00" }]
+            "#,
+        )
+        .unwrap();
+        let mut ranked = rooms.clone();
+        profile.rank(&mut ranked);
+        let titles: Vec<_> = ranked.iter().map(|(room, _)| room.title()).collect();
+        assert_eq!(
+            titles,
+            ["3-20A Idea Lab", "2-05 Study Room", "3-10A Meeting Room"]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    room::{Availability, Room},
+    snapshot::Snapshot,
+};
+
+/// How the free time slots of a room changed between two scrapes.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RoomChange {
+    pub room: Room,
+    /// The time slots that have become free, e.g. after a cancellation.
+    pub opened: Availability,
+    /// The time slots that have been booked.
+    pub closed: Availability,
+}
+
+/// The difference between two availability results for the same date.
+///
+/// Rooms are matched by their title.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, specta::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AvailabilityDiff {
+    /// The rooms that are only listed in the newer result.
+    pub appeared: Vec<(Room, Availability)>,
+    /// The rooms that are only listed in the older result.
+    pub disappeared: Vec<(Room, Availability)>,
+    /// The rooms that are listed in both results but whose free time slots differ.
+    pub changed: Vec<RoomChange>,
+}
+
+impl AvailabilityDiff {
+    pub fn between(older: &[(Room, Availability)], newer: &[(Room, Availability)]) -> Self {
+        let find = |rooms: &[(Room, Availability)], title: &str| {
+            rooms
+                .iter()
+                .find(|(room, _)| room.title() == title)
+                .map(|(_, availability)| availability.clone())
+        };
+
+        let mut diff = Self::default();
+        for (room, availability) in newer {
+            match find(older, room.title()) {
+                None => diff.appeared.push((room.clone(), availability.clone())),
+                Some(old_availability) => {
+                    let opened = availability.without(&old_availability);
+                    let closed = old_availability.without(availability);
+                    if !opened.is_empty() || !closed.is_empty() {
+                        diff.changed.push(RoomChange {
+                            room: room.clone(),
+                            opened,
+                            closed,
+                        });
+                    }
+                }
+            }
+        }
+        for (room, availability) in older {
+            if find(newer, room.title()).is_none() {
+                diff.disappeared.push((room.clone(), availability.clone()));
+            }
+        }
+        diff
+    }
+
+    /// Whether both results list the same rooms with the same free time slots.
+    pub fn is_empty(&self) -> bool {
+        self.appeared.is_empty() && self.disappeared.is_empty() && self.changed.is_empty()
+    }
+
+    /// The time slots that have become free in the room with the given title.
+    pub fn opened_in(&self, title: &str) -> Option<&Availability> {
+        self.changed
+            .iter()
+            .find(|change| change.room.title() == title)
+            .map(|change| &change.opened)
+            .or_else(|| {
+                self.appeared
+                    .iter()
+                    .find(|(room, _)| room.title() == title)
+                    .map(|(_, availability)| availability)
+            })
+            .filter(|availability| !availability.is_empty())
+    }
+}
+
+impl Snapshot {
+    /// The changes from `self` to a `newer` snapshot of the same date.
+    pub fn diff(&self, newer: &Snapshot) -> AvailabilityDiff {
+        AvailabilityDiff::between(&self.rooms, &newer.rooms)
+    }
+}
+
+impl std::fmt::Display for AvailabilityDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+        for (room, availability) in &self.appeared {
+            writeln!(f, "+ {} appeared: {availability}", room.title())?;
+        }
+        for (room, _) in &self.disappeared {
+            writeln!(f, "- {} disappeared", room.title())?;
+        }
+        for RoomChange {
+            room,
+            opened,
+            closed,
+        } in &self.changed
+        {
+            writeln!(f, "~ {}", room.title())?;
+            if !opened.is_empty() {
+                writeln!(f, "    opened: {opened}")?;
+            }
+            if !closed.is_empty() {
+                writeln!(f, "    closed: {closed}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room::fixtures::free_room as room;
+
+    #[test]
+    fn cancellation_opens_slots() {
+        let older = [room("3-20A Idea Lab", &["9:00 AM"])];
+        let newer = [room("3-20A Idea Lab", &["9:00 AM", "1:00 PM", "1:30 PM"])];
+        let diff = AvailabilityDiff::between(&older, &newer);
+        assert!(diff.appeared.is_empty() && diff.disappeared.is_empty());
+        assert_eq!(
+            diff.opened_in("3-20A Idea Lab").unwrap().to_string(),
+            "[1:00 PM, 1:30 PM]"
+        );
+        assert!(diff.changed[0].closed.is_empty());
+    }
+
+    #[test]
+    fn rooms_appear_and_disappear() {
+        let older = [
+            room("3-20A Idea Lab", &["9:00 AM"]),
+            room("2-05A Meeting Room", &[]),
+        ];
+        let newer = [
+            room("3-20A Idea Lab", &[]),
+            room("3-10B Meeting Room", &["5:00 PM"]),
+        ];
+        let diff = AvailabilityDiff::between(&older, &newer);
+        assert_eq!(diff.appeared[0].0.title(), "3-10B Meeting Room");
+        assert_eq!(diff.disappeared[0].0.title(), "2-05A Meeting Room");
+        assert_eq!(diff.changed[0].closed.to_string(), "[9:00 AM]");
+        assert!(diff.opened_in("3-20A Idea Lab").is_none());
+        assert!(diff.opened_in("3-10B Meeting Room").is_some());
+    }
+
+    #[test]
+    fn identical_results_have_no_changes() {
+        let rooms = [room("3-20A Idea Lab", &["9:00 AM"])];
+        assert!(AvailabilityDiff::between(&rooms, &rooms).is_empty());
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt::Write;
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use crate::snapshot::Snapshot;
+
+/// The free rooms for a group on a few candidate dates, e.g. for a weekly email.
+#[derive(Debug, Clone)]
+pub struct Digest {
+    pub group_size: u8,
+    /// A snapshot per candidate date, in the order in which they are listed.
+    pub days: Vec<Snapshot>,
+}
+
+/// The next `count` dates after `today` that fall on one of the `weekdays`.
+pub fn upcoming_weekdays(today: NaiveDate, weekdays: &[Weekday], count: usize) -> Vec<NaiveDate> {
+    if weekdays.is_empty() {
+        return Vec::new();
+    }
+    (1..)
+        .map(|days| today + Duration::try_days(days).unwrap())
+        .filter(|date| weekdays.contains(&date.weekday()))
+        .take(count)
+        .collect()
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Digest {
+    pub fn subject(&self) -> String {
+        match (self.days.first(), self.days.last()) {
+            (Some(first), Some(last)) if first.date != last.date => format!(
+                "Free rooms for {} people, {} to {}",
+                self.group_size,
+                first.date.format("%b %-d"),
+                last.date.format("%b %-d"),
+            ),
+            (Some(day), _) => format!(
+                "Free rooms for {} people on {}",
+                self.group_size,
+                day.date.format("%b %-d")
+            ),
+            _ => format!("Free rooms for {} people", self.group_size),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        writeln!(text, "{}", self.subject()).unwrap();
+        for day in &self.days {
+            writeln!(text).unwrap();
+            writeln!(text, "{}", day.date.format("%A, %B %-d, %Y")).unwrap();
+            let mut free = day.rooms.iter().filter(|(_, a)| !a.is_empty()).peekable();
+            if free.peek().is_none() {
+                writeln!(text, "  Every room is booked").unwrap();
+            }
+            for (room, availability) in free {
+                writeln!(text, "  {}: {availability}", room.title()).unwrap();
+            }
+        }
+        text
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        writeln!(html, "<h1>{}</h1>", escape_html(&self.subject())).unwrap();
+        for day in &self.days {
+            writeln!(html, "<h2>{}</h2>", day.date.format("%A, %B %-d, %Y")).unwrap();
+            let mut free = day.rooms.iter().filter(|(_, a)| !a.is_empty()).peekable();
+            if free.peek().is_none() {
+                writeln!(html, "<p>Every room is booked</p>").unwrap();
+                continue;
+            }
+            writeln!(html, "<ul>").unwrap();
+            for (room, availability) in free {
+                writeln!(
+                    html,
+                    "<li><strong>{}</strong>: {}</li>",
+                    escape_html(room.title()),
+                    escape_html(&availability.to_string()),
+                )
+                .unwrap();
+            }
+            writeln!(html, "</ul>").unwrap();
+        }
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::room::{fixtures, Availability};
+
+    fn digest() -> Digest {
+        let (room, free) = fixtures::free_room("3-20A Idea Lab <R&D>", &["6:00 PM"]);
+        let day = |d, rooms| Snapshot {
+            date: NaiveDate::from_ymd_opt(2024, 3, d).unwrap(),
+            group_size: 20,
+            scraped_at: Utc::now(),
+            rooms,
+        };
+        Digest {
+            group_size: 20,
+            days: vec![
+                day(14, vec![(room.clone(), free)]),
+                day(21, vec![(room, Availability::default())]),
+            ],
+        }
+    }
+
+    #[test]
+    fn text_lists_free_rooms_per_day() {
+        let text = digest().to_text();
+        assert!(text.starts_with("Free rooms for 20 people, Mar 14 to Mar 21\n"));
+        assert!(text.contains("Thursday, March 14, 2024\n  3-20A Idea Lab <R&D>: [6:00 PM]\n"));
+        assert!(text.contains("Thursday, March 21, 2024\n  Every room is booked\n"));
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        let html = digest().to_html();
+        assert!(html.contains("<strong>3-20A Idea Lab &lt;R&amp;D&gt;</strong>: [6:00 PM]"));
+    }
+
+    #[test]
+    fn upcoming_weekdays_skip_today() {
+        let thursday = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        let dates = upcoming_weekdays(thursday, &[Weekday::Thu], 2);
+        assert_eq!(
+            dates,
+            [
+                NaiveDate::from_ymd_opt(2024, 3, 21).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 3, 28).unwrap()
+            ]
+        );
+    }
+}
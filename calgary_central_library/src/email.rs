@@ -0,0 +1,219 @@
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::digest::Digest;
+
+/// How the connection to the SMTP server is secured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Plain text, e.g. for a local SMTP sink. Port 25 by default.
+    None,
+    /// Upgraded with STARTTLS. Port 587 by default.
+    #[default]
+    Starttls,
+    /// Implicit TLS. Port 465 by default.
+    Tls,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SmtpConfig {
+    pub host: String,
+    /// The default port of `security` if unset.
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    pub username: Option<String>,
+    /// The environment variable with the password, so that it isn't stored with the configuration.
+    #[serde(default)]
+    pub password_env: Option<String>,
+    /// The sender, e.g. `Calgary Rust <rooms@example.com>`.
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+/// Sends [`Digest`]s as emails with an HTML and a plain text part.
+pub struct SmtpDigestSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+#[derive(Error, Debug)]
+pub enum EmailError {
+    #[error("Invalid email address: {0}")]
+    Address(#[from] lettre::address::AddressError),
+    #[error("The environment variable {0} with the SMTP password is not set")]
+    MissingPassword(String),
+    #[error("At least one recipient must be configured")]
+    NoRecipients,
+    #[error("Failed to build the email: {0}")]
+    Message(#[from] lettre::error::Error),
+    #[error("SMTP error: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+}
+
+impl SmtpDigestSender {
+    pub fn new(config: &SmtpConfig) -> Result<Self, EmailError> {
+        let mut builder = match config.security {
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host).port(25)
+            }
+            SmtpSecurity::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+        };
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let Some(username) = &config.username {
+            let password = match &config.password_env {
+                Some(var) => {
+                    std::env::var(var).map_err(|_| EmailError::MissingPassword(var.clone()))?
+                }
+                None => String::new(),
+            };
+            builder = builder.credentials(Credentials::new(username.clone(), password));
+        }
+        if config.to.is_empty() {
+            return Err(EmailError::NoRecipients);
+        }
+        Ok(Self {
+            transport: builder.build(),
+            from: config.from.parse()?,
+            to: config
+                .to
+                .iter()
+                .map(|to| to.parse())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub async fn send(&self, digest: &Digest) -> Result<(), EmailError> {
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(digest.subject());
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message.multipart(MultiPart::alternative_plain_html(
+            digest.to_text(),
+            digest.to_html(),
+        ))?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chrono::NaiveDate;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    /// Accepts a single SMTP session on a random local port and records the commands and the message.
+    async fn smtp_sink() -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let recorded = lines.clone();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = BufReader::new(reader);
+            writer.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+            let mut in_data = false;
+            let mut line = String::new();
+            while reader.read_line(&mut line).await.unwrap() > 0 {
+                let command = line.trim_end().to_owned();
+                line.clear();
+                recorded.lock().unwrap().push(command.clone());
+                let reply: &[u8] = if in_data {
+                    if command != "." {
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if command.starts_with("EHLO") {
+                    b"250 sink\r\n"
+                } else if command == "DATA" {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if command == "QUIT" {
+                    writer.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+        });
+        (port, lines)
+    }
+
+    #[tokio::test]
+    async fn digest_is_sent_to_every_recipient() {
+        let (port, lines) = smtp_sink().await;
+        let sender = SmtpDigestSender::new(&SmtpConfig {
+            host: "127.0.0.1".to_owned(),
+            port: Some(port),
+            security: SmtpSecurity::None,
+            username: None,
+            password_env: None,
+            from: "Calgary Rust <rooms@example.com>".to_owned(),
+            to: vec!["a@example.com".to_owned(), "b@example.com".to_owned()],
+        })
+        .unwrap();
+        let digest = Digest {
+            group_size: 20,
+            days: vec![crate::Snapshot {
+                date: NaiveDate::from_ymd_opt(2024, 3, 14).unwrap(),
+                group_size: 20,
+                scraped_at: chrono::Utc::now(),
+                rooms: Vec::new(),
+            }],
+        };
+        sender.send(&digest).await.unwrap();
+
+        let lines = lines.lock().unwrap();
+        assert!(lines.contains(&"RCPT TO:<a@example.com>".to_owned()));
+        assert!(lines.contains(&"RCPT TO:<b@example.com>".to_owned()));
+        assert!(lines.contains(&"Subject: Free rooms for 20 people on Mar 14".to_owned()));
+        assert!(lines
+            .iter()
+            .any(|l| l.starts_with("Content-Type: text/html")));
+        assert!(lines.iter().any(|l| l.contains("Every room is booked")));
+    }
+
+    #[test]
+    fn recipients_are_required() {
+        let config = SmtpConfig {
+            host: "localhost".to_owned(),
+            port: None,
+            security: SmtpSecurity::None,
+            username: None,
+            password_env: None,
+            from: "rooms@example.com".to_owned(),
+            to: Vec::new(),
+        };
+        assert!(matches!(
+            SmtpDigestSender::new(&config),
+            Err(EmailError::NoRecipients)
+        ));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
+    booking_url,
     room::{Availability, Room, RoomChoice},
-    AsyncClient, AsyncElement, AsyncQuerySelector,
+    AsyncClient, AsyncElement, AsyncQuerySelector, ScrapeProgress,
 };
 use fantoccini::{elements::Element, Locator};
 use thiserror::Error;
@@ -42,6 +43,57 @@ pub enum FindSearchButtonError {
     FantocciniError(#[from] fantoccini::error::CmdError),
 }
 
+impl AvailableRoomsError {
+    /// Whether the booking page no longer has the structure that the scraper expects.
+    pub fn is_page_structure_changed(&self) -> bool {
+        match self {
+            Self::FindSearchButtonError(e) => e.is_page_structure_changed(),
+            Self::QuerySelectorError(e) | Self::FailedGetText(e) | Self::ClickError(e) => {
+                e.is_miss()
+            }
+            Self::NavigateToUrlError(_) => false,
+        }
+    }
+
+    /// Whether the booking page could not be loaded.
+    pub fn is_navigation_failed(&self) -> bool {
+        matches!(self, Self::NavigateToUrlError(_))
+    }
+
+    /// Whether the connection to the WebDriver server was lost in the middle of the scrape.
+    pub fn is_web_driver_connection_lost(&self) -> bool {
+        match self {
+            Self::FindSearchButtonError(FindSearchButtonError::FantocciniError(e))
+            | Self::NavigateToUrlError(e)
+            | Self::QuerySelectorError(e)
+            | Self::FailedGetText(e)
+            | Self::ClickError(e) => is_connection_lost(e),
+            Self::FindSearchButtonError(_) => false,
+        }
+    }
+}
+
+impl FindSearchButtonError {
+    /// Whether the booking page no longer has the structure that the scraper expects.
+    pub fn is_page_structure_changed(&self) -> bool {
+        match self {
+            Self::NoButtonFound | Self::MoreThanOneButtonFound => true,
+            Self::FantocciniError(e) => e.is_miss(),
+        }
+    }
+}
+
+/// Whether the session can't be used anymore: the connection broke, or the WebDriver server
+/// no longer knows the session or its window, e.g. after the server restarted.
+fn is_connection_lost(e: &fantoccini::error::CmdError) -> bool {
+    use fantoccini::error::CmdError;
+    match e {
+        CmdError::Lost(_) | CmdError::Failed(_) | CmdError::NoSuchWindow(_) => true,
+        CmdError::Standard(e) => e.error() == "invalid session id",
+        _ => false,
+    }
+}
+
 impl AsyncQuerySelector for Element {
     type Element = Self;
 
@@ -121,27 +173,26 @@ impl AsyncClient for ClientImpl {
     }
 
     type AvailableRoomsError = AvailableRoomsError;
-    async fn available_rooms(
+    async fn available_rooms_with_progress(
         &self,
         date: chrono::prelude::NaiveDate,
         group_size: u8,
+        mut on_progress: impl FnMut(ScrapeProgress<'_>),
     ) -> Result<Vec<(crate::room::Room, crate::room::Availability)>, Self::AvailableRoomsError>
     {
         let mut rooms = Vec::new();
-        let booking_url = format!(
-                "https://calgarylibrary.ca/events-and-programs/book-a-space/book-a-room/?date={}&location=1&groupsize={}",
-                date.format("%Y-%m-%d"),
-                group_size
-            );
+        let booking_url = booking_url(date, group_size);
         self.navigate_to_url(&booking_url)
             .await
             .map_err(AvailableRoomsError::NavigateToUrlError)?;
         let _search_button = self.find_search_button().await?;
-        for room_elem in self
+        let room_elems = self
             .query_selector_all(".room-booking-card")
             .await
-            .map_err(AvailableRoomsError::QuerySelectorError)?
-        {
+            .map_err(AvailableRoomsError::QuerySelectorError)?;
+        let total = room_elems.len();
+        on_progress(ScrapeProgress::PageLoaded { total });
+        for (index, room_elem) in room_elems.into_iter().enumerate() {
             let title: Element = room_elem
                 .query_selector(".uk-card-title")
                 .await
@@ -186,6 +237,12 @@ impl AsyncClient for ClientImpl {
                 v
             };
             let availability = Availability::from(time_slots);
+            on_progress(ScrapeProgress::RoomParsed {
+                index,
+                total,
+                room: &room,
+                availability: &availability,
+            });
             rooms.push((room, availability));
         }
 
@@ -0,0 +1,266 @@
+use std::fmt;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::{booking::Booking, snapshot::Snapshot, time::TIMEZONE};
+
+const LOCATION: &str = "Calgary Central Library, 800 3 St SE, Calgary, AB";
+
+/// The Mountain Time rules since 2007, which [`library_time`](crate::library_time) follows.
+const VTIMEZONE: &[&str] = &[
+    "BEGIN:VTIMEZONE",
+    "TZID:America/Edmonton",
+    "BEGIN:DAYLIGHT",
+    "TZOFFSETFROM:-0700",
+    "TZOFFSETTO:-0600",
+    "TZNAME:MDT",
+    "DTSTART:19700308T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU",
+    "END:DAYLIGHT",
+    "BEGIN:STANDARD",
+    "TZOFFSETFROM:-0600",
+    "TZOFFSETTO:-0700",
+    "TZNAME:MST",
+    "DTSTART:19701101T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU",
+    "END:STANDARD",
+    "END:VTIMEZONE",
+];
+
+/// An iCalendar (RFC 5545) document with free rooms as tentative events and bookings as confirmed ones.
+pub struct Calendar {
+    name: String,
+    dtstamp: DateTime<Utc>,
+    events: Vec<Event>,
+}
+
+struct Event {
+    uid: String,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    summary: String,
+    location: String,
+    description: String,
+    /// Whether the event is a booking rather than a free window.
+    confirmed: bool,
+}
+
+/// Turns a room title into something that can be part of a UID, e.g. `3-20a-idea-lab`.
+fn slug(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes a content line, folded so that no line is longer than 75 octets.
+fn write_line(f: &mut fmt::Formatter<'_>, line: &str) -> fmt::Result {
+    let mut rest = line;
+    let mut limit = 75;
+    while rest.len() > limit {
+        let mut end = limit;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        write!(f, "{}\r\n ", &rest[..end])?;
+        rest = &rest[end..];
+        // The leading space of a continuation line counts towards its length
+        limit = 74;
+    }
+    write!(f, "{rest}\r\n")
+}
+
+impl Calendar {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            dtstamp: Utc::now(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Adds a tentative event for every range of free time of every room in the snapshot.
+    pub fn add_free_windows(&mut self, snapshot: &Snapshot) -> &mut Self {
+        for (room, availability) in &snapshot.rooms {
+            for (from, to) in availability.free_ranges() {
+                let mut description = Vec::new();
+                if let Some(floor) = room.floor() {
+                    description.push(format!("Floor {floor}."));
+                }
+                if let Some(capacity) = room.capacity() {
+                    description.push(format!("Up to {capacity} people."));
+                }
+                description.push(format!(
+                    "Free as of {}.",
+                    snapshot.scraped_at.format("%Y-%m-%d %H:%M UTC")
+                ));
+                self.events.push(Event {
+                    uid: format!(
+                        "free-{}-{}-{}-{}@booking-rooms",
+                        snapshot.date.format("%Y%m%d"),
+                        from.format("%H%M"),
+                        slug(room.title()),
+                        snapshot.group_size,
+                    ),
+                    start: snapshot.date.and_time(from),
+                    end: snapshot.date.and_time(to),
+                    summary: format!("Free: {}", room.title()),
+                    location: format!("{}, {LOCATION}", room.title()),
+                    description: description.join(" "),
+                    confirmed: false,
+                });
+            }
+        }
+        self
+    }
+
+    pub fn add_booking(&mut self, booking: &Booking) -> &mut Self {
+        let room = &booking.room;
+        let mut description = Vec::new();
+        if let Some(floor) = room.floor() {
+            description.push(format!("Floor {floor}."));
+        }
+        description.push(format!("For {} people.", booking.group_size));
+        description.push(format!("Confirmation {}.", booking.confirmation_id));
+        self.events.push(Event {
+            uid: format!("booking-{}@booking-rooms", slug(&booking.confirmation_id)),
+            start: booking.date.and_time(booking.from),
+            end: booking.date.and_time(booking.to),
+            summary: format!("Booked: {}", room.title()),
+            location: format!("{}, {LOCATION}", room.title()),
+            description: description.join(" "),
+            confirmed: true,
+        });
+        self
+    }
+}
+
+impl fmt::Display for Calendar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dtstamp = self.dtstamp.format("%Y%m%dT%H%M%SZ");
+        write_line(f, "BEGIN:VCALENDAR")?;
+        write_line(f, "VERSION:2.0")?;
+        write_line(f, "PRODID:-//Calgary Rust//booking-rooms//EN")?;
+        write_line(f, "CALSCALE:GREGORIAN")?;
+        write_line(f, &format!("X-WR-CALNAME:{}", escape_text(&self.name)))?;
+        write_line(f, &format!("X-WR-TIMEZONE:{TIMEZONE}"))?;
+        for line in VTIMEZONE {
+            write_line(f, line)?;
+        }
+        for event in &self.events {
+            write_line(f, "BEGIN:VEVENT")?;
+            write_line(f, &format!("UID:{}", event.uid))?;
+            write_line(f, &format!("DTSTAMP:{dtstamp}"))?;
+            write_line(
+                f,
+                &format!(
+                    "DTSTART;TZID={TIMEZONE}:{}",
+                    event.start.format("%Y%m%dT%H%M%S")
+                ),
+            )?;
+            write_line(
+                f,
+                &format!(
+                    "DTEND;TZID={TIMEZONE}:{}",
+                    event.end.format("%Y%m%dT%H%M%S")
+                ),
+            )?;
+            write_line(f, &format!("SUMMARY:{}", escape_text(&event.summary)))?;
+            write_line(f, &format!("LOCATION:{}", escape_text(&event.location)))?;
+            write_line(
+                f,
+                &format!("DESCRIPTION:{}", escape_text(&event.description)),
+            )?;
+            if event.confirmed {
+                write_line(f, "STATUS:CONFIRMED")?;
+                write_line(f, "TRANSP:OPAQUE")?;
+            } else {
+                write_line(f, "STATUS:TENTATIVE")?;
+                write_line(f, "TRANSP:TRANSPARENT")?;
+            }
+            write_line(f, "END:VEVENT")?;
+        }
+        write_line(f, "END:VCALENDAR")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use super::*;
+    use crate::room::{fixtures, Availability, Room};
+
+    fn idea_lab() -> Room {
+        fixtures::room("3-20A Idea Lab")
+    }
+
+    #[test]
+    fn free_windows_are_tentative_events() {
+        let snapshot = Snapshot {
+            date: NaiveDate::from_ymd_opt(2024, 3, 14).unwrap(),
+            group_size: 20,
+            scraped_at: Utc::now(),
+            rooms: vec![(
+                idea_lab(),
+                Availability::from(["6:00 PM", "6:30 PM"].map(String::from)),
+            )],
+        };
+        let ics = Calendar::new("Free rooms")
+            .add_free_windows(&snapshot)
+            .to_string();
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("\r\nDTSTART;TZID=America/Edmonton:20240314T180000\r\n"));
+        assert!(ics.contains("\r\nDTEND;TZID=America/Edmonton:20240314T190000\r\n"));
+        assert!(ics.contains("\r\nSTATUS:TENTATIVE\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+    }
+
+    #[test]
+    fn bookings_carry_the_floor_and_the_confirmation_id() {
+        let booking = Booking {
+            confirmation_id: "ABC123".to_owned(),
+            room: idea_lab(),
+            date: NaiveDate::from_ymd_opt(2024, 3, 14).unwrap(),
+            from: NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+            to: NaiveTime::from_hms_opt(21, 0, 0).unwrap(),
+            group_size: 20,
+        };
+        let ics = Calendar::new("Bookings").add_booking(&booking).to_string();
+        assert!(ics.contains("\r\nSTATUS:CONFIRMED\r\n"));
+        assert!(ics.contains("\r\nDESCRIPTION:Floor 3. For 20 people. Confirmation ABC123.\r\n"));
+        assert!(ics.contains(
+            "\r\nLOCATION:3-20A Idea Lab\\, Calgary Central Library\\, 800 3 St SE\\, Calgary\\"
+        ));
+    }
+
+    #[test]
+    fn long_lines_are_folded() {
+        let ics = Calendar::new("é".repeat(100)).to_string();
+        for line in ics.split("\r\n") {
+            assert!(line.len() <= 75, "{line:?} is too long");
+        }
+        assert!(ics.contains("\r\n é"));
+    }
+}
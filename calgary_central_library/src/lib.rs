@@ -1,10 +1,82 @@
+#![allow(async_fn_in_trait)]
+
 use chrono::NaiveDate;
 use std::fmt::Debug;
 
+mod booking;
+#[cfg(feature = "config")]
+mod config;
+mod diff;
+mod digest;
+#[cfg(feature = "email")]
+mod email;
 mod fantoccini_impl;
+mod ics;
+#[cfg(feature = "remote")]
+mod remote;
 mod room;
+mod snapshot;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod time;
+mod watch;
+#[cfg(feature = "webhook")]
+mod webhook;
 
+pub use booking::Booking;
+#[cfg(feature = "config")]
+pub use config::{
+    Config, ConfigError, Credentials, ParseWebDriverEndpointError, Profile, WebDriverEndpoint,
+};
+pub use diff::{AvailabilityDiff, RoomChange};
+pub use digest::{upcoming_weekdays, Digest};
+#[cfg(feature = "email")]
+pub use email::{EmailError, SmtpConfig, SmtpDigestSender, SmtpSecurity};
+pub use ics::Calendar;
+#[cfg(feature = "remote")]
+pub use remote::{RemoteClient, RemoteError};
+#[cfg(feature = "test-fixtures")]
+pub use room::fixtures;
 pub use room::{Availability, Room};
+pub use snapshot::{Snapshot, SnapshotStore};
+#[cfg(feature = "sqlite")]
+pub use sqlite::{default_database_path, SqliteSnapshotStore, SqliteSnapshotStoreError};
+pub use time::{library_time, library_today, TIMEZONE};
+pub use watch::{
+    Alert, InvalidTimeWindow, Notification, Notifier, Schedule, StdoutNotifier, TimeWindow,
+    WatchError, WatchRule, Watcher,
+};
+#[cfg(feature = "webhook")]
+pub use webhook::{WebhookConfig, WebhookError, WebhookFormat, WebhookNotifier, SIGNATURE_HEADER};
+
+/// The directories where the front ends keep their data and their configuration.
+#[cfg(any(feature = "config", feature = "sqlite"))]
+fn project_dirs() -> Option<directories::ProjectDirs> {
+    directories::ProjectDirs::from("ca", "Calgary Rust", "booking-rooms")
+}
+
+/// The URL of the booking page of the Central Library that lists the rooms for the given day.
+pub fn booking_url(date: NaiveDate, group_size: u8) -> String {
+    format!(
+        "https://calgarylibrary.ca/events-and-programs/book-a-space/book-a-room/?date={}&location=1&groupsize={}",
+        date.format("%Y-%m-%d"),
+        group_size
+    )
+}
+
+/// A step of [`AsyncClient::available_rooms_with_progress`].
+#[derive(Debug)]
+pub enum ScrapeProgress<'a> {
+    /// The booking page has been loaded and lists `total` rooms.
+    PageLoaded { total: usize },
+    /// The room at `index` (counting from zero) out of `total` has been parsed.
+    RoomParsed {
+        index: usize,
+        total: usize,
+        room: &'a Room,
+        availability: &'a Availability,
+    },
+}
 
 pub trait AsyncQuerySelector {
     type Element: AsyncElement;
@@ -43,6 +115,17 @@ pub trait AsyncClient: Sized + AsyncQuerySelector {
         &self,
         date: NaiveDate,
         group_size: u8,
+    ) -> Result<Vec<(Room, Availability)>, Self::AvailableRoomsError> {
+        self.available_rooms_with_progress(date, group_size, |_| {})
+            .await
+    }
+
+    /// Same as [`AsyncClient::available_rooms`] but reports every room as soon as it is parsed.
+    async fn available_rooms_with_progress(
+        &self,
+        date: NaiveDate,
+        group_size: u8,
+        on_progress: impl FnMut(ScrapeProgress<'_>),
     ) -> Result<Vec<(Room, Availability)>, Self::AvailableRoomsError>;
 
     type FindSearchButtonError: Debug;
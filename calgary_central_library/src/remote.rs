@@ -0,0 +1,218 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    room::{Availability, Room},
+    snapshot::Snapshot,
+};
+
+/// The `location` identifier that the library website uses for the Central Library.
+const CENTRAL_LIBRARY_BRANCH_ID: u32 = 1;
+
+/// Checks the rooms through a running crawling server (`ccl_headless_browser_server`)
+/// instead of a WebDriver session of its own.
+#[derive(Clone)]
+pub struct RemoteClient {
+    client: reqwest::Client,
+    /// The URL of the server without a trailing slash, e.g. `http://localhost:3000`.
+    base_url: String,
+}
+
+#[derive(Error, Debug)]
+pub enum RemoteError {
+    #[error("Failed to reach the crawling server: {0}")]
+    Request(#[from] reqwest::Error),
+    /// The error body of the server, e.g. `web_driver_unreachable`.
+    #[error("The crawling server responded with {status} ({code}): {message}")]
+    Api {
+        status: StatusCode,
+        code: String,
+        message: String,
+        retryable: bool,
+    },
+}
+
+#[derive(Deserialize)]
+struct RoomsResponse {
+    scraped_at: DateTime<Utc>,
+    rooms: Vec<(Room, Availability)>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    code: String,
+    message: String,
+    retryable: bool,
+}
+
+impl RemoteError {
+    /// Whether repeating the same request later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            RemoteError::Request(_) => true,
+            RemoteError::Api { retryable, .. } => *retryable,
+        }
+    }
+}
+
+impl RemoteClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base_url: String = base_url.into();
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Sends a GET request and turns the error bodies of the server into [`RemoteError::Api`].
+    async fn send(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<reqwest::Response, RemoteError> {
+        let response = self
+            .client
+            .get(format!("{}{path}", self.base_url))
+            .query(query)
+            .send()
+            .await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await?;
+        let ErrorBody {
+            code,
+            message,
+            retryable,
+        } = serde_json::from_str(&body).unwrap_or(ErrorBody {
+            code: "unknown".to_owned(),
+            message: body,
+            retryable: status.is_server_error(),
+        });
+        Err(RemoteError::Api {
+            status,
+            code,
+            message,
+            retryable,
+        })
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, RemoteError> {
+        Ok(self.send(path, query).await?.json().await?)
+    }
+
+    /// Fails unless the server is up, so that a wrong URL is noticed before the first query.
+    pub async fn check_health(&self) -> Result<(), RemoteError> {
+        self.send("/healthz", &[]).await?;
+        Ok(())
+    }
+
+    /// The rooms of the Central Library, which the server may take from its cache.
+    pub async fn available_rooms(
+        &self,
+        date: NaiveDate,
+        group_size: u8,
+    ) -> Result<Snapshot, RemoteError> {
+        let RoomsResponse { scraped_at, rooms } = self
+            .get(
+                &format!("/branches/{CENTRAL_LIBRARY_BRANCH_ID}/rooms"),
+                &[
+                    ("date", date.to_string()),
+                    ("group_size", group_size.to_string()),
+                ],
+            )
+            .await?;
+        Ok(Snapshot {
+            date,
+            group_size,
+            scraped_at,
+            rooms,
+        })
+    }
+
+    /// The last rooms that the server recorded for the day, without scraping them again.
+    pub async fn latest_rooms(
+        &self,
+        date: NaiveDate,
+        group_size: u8,
+    ) -> Result<Snapshot, RemoteError> {
+        self.get(
+            &format!("/branches/{CENTRAL_LIBRARY_BRANCH_ID}/rooms/latest"),
+            &[
+                ("date", date.to_string()),
+                ("group_size", group_size.to_string()),
+            ],
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{extract::Query, routing::get, Json, Router};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::room::fixtures;
+
+    async fn rooms(Query(query): Query<Value>) -> (axum::http::StatusCode, Json<Value>) {
+        if query["group_size"] == "0" {
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "code": "invalid_group_size",
+                    "message": "The group size must be at least 1",
+                    "retryable": false
+                })),
+            );
+        }
+        (
+            axum::http::StatusCode::OK,
+            Json(json!({
+                "scraped_at": "2026-10-19T10:00:00Z",
+                "freshness": "fresh",
+                "rooms": [fixtures::free_room("3-20A Idea Lab", &["6:00 PM", "6:30 PM"])]
+            })),
+        )
+    }
+
+    /// Serves a stand-in for the crawling server on a random local port and returns its URL.
+    async fn serve() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/branches/1/rooms", get(rooms));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}/")
+    }
+
+    #[tokio::test]
+    async fn rooms_are_a_snapshot() {
+        let client = RemoteClient::new(serve().await);
+        let date = NaiveDate::from_ymd_opt(2026, 11, 12).unwrap();
+        let snapshot = client.available_rooms(date, 20).await.unwrap();
+        assert_eq!(snapshot.date, date);
+        assert_eq!(snapshot.group_size, 20);
+        assert_eq!(snapshot.rooms[0].0.title(), "3-20A Idea Lab");
+        assert_eq!(snapshot.rooms[0].1.to_string(), "[6:00 PM, 6:30 PM]");
+    }
+
+    #[tokio::test]
+    async fn error_bodies_are_kept() {
+        let client = RemoteClient::new(serve().await);
+        let date = NaiveDate::from_ymd_opt(2026, 11, 12).unwrap();
+        let err = client.available_rooms(date, 0).await.unwrap_err();
+        assert!(!err.is_retryable());
+        assert!(matches!(err, RemoteError::Api { code, .. } if code == "invalid_group_size"));
+    }
+}
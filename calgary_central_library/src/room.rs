@@ -1,3 +1,4 @@
+use chrono::{NaiveTime, Timelike};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
@@ -20,7 +21,8 @@ fn stringly_capacity(haystack: &str) -> Option<&str> {
 }
 
 // TODO: add historical capacity data
-#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub(crate) enum KnownRoom {
     // 2-05A Meeting Room
     R205AMeetingRoom,
@@ -55,13 +57,15 @@ pub(crate) enum KnownRoom {
 }
 
 /// Either a specific room or an unknown room.
-#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub(crate) enum RoomChoice {
     KnownRoom(KnownRoom),
     UnknownRoom,
 }
 
-#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Room {
     choice: RoomChoice,
     title: String,
@@ -72,9 +76,11 @@ pub struct Room {
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, specta::Type,
 )]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 struct TimeSlot(u8);
 
-#[derive(Serialize, Deserialize, specta::Type)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Availability(Vec<TimeSlot>);
 
 impl RoomChoice {
@@ -135,16 +141,27 @@ impl TimeSlot {
         Some(Self(time_slot))
     }
 
-    pub(crate) fn to_label(&self) -> String {
+    /// The time slot that contains `time`.
+    pub(crate) fn from_time(time: NaiveTime) -> Self {
+        let hour = Self::add_signed_hours(time.hour() as u8, -5);
+        Self(hour * 2 + (time.minute() / 30) as u8)
+    }
+
+    pub(crate) fn start_time(self) -> NaiveTime {
         let hour = Self::add_signed_hours(self.0 / 2, 5);
-        let min = if self.0 % 2 == 0 { "00" } else { "30" };
+        NaiveTime::from_hms_opt(hour.into(), u32::from(self.0 % 2) * 30, 0).unwrap()
+    }
+
+    pub(crate) fn to_label(self) -> String {
+        let hour = Self::add_signed_hours(self.0 / 2, 5);
+        let min = if self.0.is_multiple_of(2) { "00" } else { "30" };
         let am_pm = if hour < 12 { "AM" } else { "PM" };
         let hour = if hour == 0 { 12 } else { hour % 12 };
         format!("{}:{} {}", hour, min, am_pm)
     }
 
     #[cfg(test)]
-    pub(crate) fn to_discriminant(&self) -> u8 {
+    pub(crate) fn to_discriminant(self) -> u8 {
         self.0
     }
 }
@@ -166,6 +183,25 @@ impl Room {
             }
         }
     }
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The floor of the room, e.g. 3 for "3-20A Idea Lab".
+    pub fn floor(&self) -> Option<u8> {
+        let (floor, _) = self.title.split_once('-')?;
+        floor.parse().ok()
+    }
+
+    /// The capacity of the room as stated in its description, if it could be recognized.
+    pub fn capacity(&self) -> Option<u8> {
+        self.inferred_capacity
+    }
+
     pub(crate) fn new(choice: RoomChoice, title: String, description: String) -> Self {
         let inferred_capacity = Self::infer_capacity_from_description(description.as_str());
         Self {
@@ -183,13 +219,82 @@ where
 {
     fn from(it: It) -> Self {
         let mut time_slots = Vec::new();
-        for time_slot in it.into_iter().filter_map(|s| TimeSlot::from_label(s)) {
+        for time_slot in it.into_iter().filter_map(TimeSlot::from_label) {
             time_slots.push(time_slot);
         }
         Self(time_slots)
     }
 }
 
+impl Availability {
+    /// Whether there is no free time slot.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether every half-hour time slot that overlaps `from..to` is free.
+    /// An empty or inverted range is never free.
+    pub fn is_free_between(&self, from: NaiveTime, to: NaiveTime) -> bool {
+        if to <= from {
+            return false;
+        }
+        let mut time_slot = TimeSlot::from_time(from);
+        while time_slot.start_time() < to {
+            if !self.0.contains(&time_slot) {
+                return false;
+            }
+            time_slot = TimeSlot(time_slot.0 + 1);
+            if time_slot.0 >= 48 {
+                break;
+            }
+        }
+        true
+    }
+
+    /// The free time as ranges of consecutive time slots, e.g. 6:00 PM to 7:30 PM.
+    pub fn free_ranges(&self) -> Vec<(NaiveTime, NaiveTime)> {
+        let mut time_slots = self.0.clone();
+        time_slots.sort();
+        time_slots.dedup();
+        let mut ranges: Vec<(TimeSlot, TimeSlot)> = Vec::new();
+        for time_slot in time_slots {
+            match ranges.last_mut() {
+                Some((_, last)) if last.0 + 1 == time_slot.0 => *last = time_slot,
+                _ => ranges.push((time_slot, time_slot)),
+            }
+        }
+        ranges
+            .into_iter()
+            .map(|(first, last)| {
+                let end = last.start_time() + chrono::Duration::try_minutes(30).unwrap();
+                (first.start_time(), end)
+            })
+            .collect()
+    }
+
+    /// The free time slots that start in `from..to`.
+    pub fn within(&self, from: NaiveTime, to: NaiveTime) -> Availability {
+        Self(
+            self.0
+                .iter()
+                .copied()
+                .filter(|time_slot| (from..to).contains(&time_slot.start_time()))
+                .collect(),
+        )
+    }
+
+    /// The time slots that are free in `self` but not in `other`.
+    pub fn without(&self, other: &Availability) -> Availability {
+        Self(
+            self.0
+                .iter()
+                .copied()
+                .filter(|time_slot| !other.0.contains(time_slot))
+                .collect(),
+        )
+    }
+}
+
 impl std::fmt::Display for Availability {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.0.is_empty() {
@@ -208,6 +313,37 @@ impl std::fmt::Display for Availability {
     }
 }
 
+/// Rooms for the tests of this crate, and of the crates that depend on it
+/// with the `test-fixtures` feature.
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod fixtures {
+    use super::*;
+
+    /// A room titled `title` for up to 24 people.
+    pub fn room(title: &str) -> Room {
+        described_room(title, "It can accommodate up to 24 people")
+    }
+
+    /// A room whose capacity is inferred from its `description`, as on the library's site.
+    pub fn described_room(title: &str, description: &str) -> Room {
+        Room::new(
+            RoomChoice::from_title(title),
+            title.to_owned(),
+            description.to_owned(),
+        )
+    }
+
+    /// The availability of a room that is free at the time slots labelled `labels`, e.g. `6:00 PM`.
+    pub fn free(labels: &[&str]) -> Availability {
+        Availability::from(labels.iter().map(|label| label.to_string()))
+    }
+
+    /// A [`room`] that is free at the time slots labelled `labels`.
+    pub fn free_room(title: &str, labels: &[&str]) -> (Room, Availability) {
+        (room(title), free(labels))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -247,6 +383,41 @@ mod tests {
         assert_eq!(time_slot.to_discriminant(), 0);
     }
 
+    #[test]
+    fn time_slots_round_trip_through_times() {
+        let time_slot = TimeSlot::from_label("6:30 PM").unwrap();
+        assert_eq!(
+            time_slot.start_time(),
+            NaiveTime::from_hms_opt(18, 30, 0).unwrap()
+        );
+        assert_eq!(TimeSlot::from_time(time_slot.start_time()), time_slot);
+    }
+
+    #[test]
+    fn consecutive_time_slots_are_merged() {
+        let labels = ["9:00 AM", "6:00 PM", "6:30 PM", "7:00 PM"];
+        let availability = Availability::from(labels.map(String::from));
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert_eq!(
+            availability.free_ranges(),
+            [(time(9, 0), time(9, 30)), (time(18, 0), time(19, 30))]
+        );
+    }
+
+    #[test]
+    fn inverted_ranges_are_not_free() {
+        let availability = Availability::from(["6:00 PM", "6:30 PM"].map(String::from));
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        assert!(availability.is_free_between(time(18), time(19)));
+        assert!(!availability.is_free_between(time(19), time(18)));
+        assert!(!availability.is_free_between(time(18), time(18)));
+    }
+
+    #[test]
+    fn floor_is_the_prefix_of_the_title() {
+        assert_eq!(fixtures::room("3-20A Idea Lab").floor(), Some(3));
+    }
+
     #[test]
     fn check_timeslot() {
         let time_slot_a = TimeSlot::from_label("10:00 AM").unwrap();
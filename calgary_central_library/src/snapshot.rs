@@ -0,0 +1,40 @@
+use std::fmt::Debug;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::room::{Availability, Room};
+
+/// The rooms that were available for a group of a given size on a given day,
+/// as seen at `scraped_at`.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Snapshot {
+    pub date: NaiveDate,
+    pub group_size: u8,
+    pub scraped_at: DateTime<Utc>,
+    pub rooms: Vec<(Room, Availability)>,
+}
+
+/// A history of [`Snapshot`]s.
+pub trait SnapshotStore {
+    type SaveError: Debug;
+    fn save(&self, snapshot: &Snapshot) -> Result<(), Self::SaveError>;
+
+    type LatestError: Debug;
+    /// The most recent snapshot for `date`, for any group size if `group_size` is `None`.
+    fn latest(
+        &self,
+        date: NaiveDate,
+        group_size: Option<u8>,
+    ) -> Result<Option<Snapshot>, Self::LatestError>;
+
+    type HistoryError: Debug;
+    /// Up to `limit` snapshots for `date` and `group_size`, the most recent first.
+    fn history(
+        &self,
+        date: NaiveDate,
+        group_size: u8,
+        limit: usize,
+    ) -> Result<Vec<Snapshot>, Self::HistoryError>;
+}
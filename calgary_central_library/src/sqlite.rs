@@ -0,0 +1,316 @@
+use std::{path::PathBuf, sync::Mutex};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use thiserror::Error;
+
+use crate::{
+    booking::Booking,
+    room::{Availability, Room},
+    snapshot::{Snapshot, SnapshotStore},
+};
+
+/// A [`SnapshotStore`] in a SQLite database, which also keeps a ledger of the [`Booking`]s
+/// that were made on the library's site.
+pub struct SqliteSnapshotStore(Mutex<Connection>);
+
+#[derive(Error, Debug)]
+pub enum SqliteSnapshotStoreError {
+    #[error("Failed to create the directory of the database")]
+    CreateDirError(#[from] std::io::Error),
+    #[error("SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+    #[error("Failed to (de)serialize a room: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS snapshots (
+        id INTEGER PRIMARY KEY,
+        date TEXT NOT NULL,
+        group_size INTEGER NOT NULL,
+        scraped_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS snapshots_by_date ON snapshots (date, group_size, scraped_at);
+    CREATE TABLE IF NOT EXISTS snapshot_rooms (
+        snapshot_id INTEGER NOT NULL REFERENCES snapshots (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        room TEXT NOT NULL,
+        availability TEXT NOT NULL,
+        PRIMARY KEY (snapshot_id, position)
+    );
+    CREATE TABLE IF NOT EXISTS bookings (
+        confirmation_id TEXT PRIMARY KEY,
+        date TEXT NOT NULL,
+        start TEXT NOT NULL,
+        booking TEXT NOT NULL
+    );
+";
+
+/// The database that the command-line app, the crawling server and the desktop app share by default.
+pub fn default_database_path() -> Option<PathBuf> {
+    Some(crate::project_dirs()?.data_dir().join("snapshots.sqlite3"))
+}
+
+impl SqliteSnapshotStore {
+    /// Opens or creates the database at `path`.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, SqliteSnapshotStoreError> {
+        let path = path.into();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, SqliteSnapshotStoreError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, SqliteSnapshotStoreError> {
+        connection.pragma_update(None, "foreign_keys", "ON")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self(Mutex::new(connection)))
+    }
+
+    /// Adds the booking to the ledger, replacing any booking with the same confirmation id.
+    pub fn record_booking(&self, booking: &Booking) -> Result<(), SqliteSnapshotStoreError> {
+        let connection = self.0.lock().unwrap();
+        connection.execute(
+            "INSERT OR REPLACE INTO bookings (confirmation_id, date, start, booking)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                booking.confirmation_id,
+                booking.date,
+                booking.from,
+                serde_json::to_string(booking)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The bookings in the ledger on or after `since`, or all of them, in chronological order.
+    pub fn bookings(
+        &self,
+        since: Option<NaiveDate>,
+    ) -> Result<Vec<Booking>, SqliteSnapshotStoreError> {
+        let connection = self.0.lock().unwrap();
+        let mut statement = connection.prepare_cached(
+            "SELECT booking FROM bookings WHERE ?1 IS NULL OR date >= ?1 ORDER BY date, start",
+        )?;
+        let rows = statement.query_map([since], |row| row.get::<_, String>(0))?;
+        let mut bookings = Vec::new();
+        for row in rows {
+            bookings.push(serde_json::from_str(&row?)?);
+        }
+        Ok(bookings)
+    }
+
+    /// Removes the booking from the ledger and returns it, if it was there.
+    pub fn remove_booking(
+        &self,
+        confirmation_id: &str,
+    ) -> Result<Option<Booking>, SqliteSnapshotStoreError> {
+        let connection = self.0.lock().unwrap();
+        let booking = connection
+            .query_row(
+                "DELETE FROM bookings WHERE confirmation_id = ?1 RETURNING booking",
+                [confirmation_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(booking.map(|b| serde_json::from_str(&b)).transpose()?)
+    }
+
+    fn rooms(
+        connection: &Connection,
+        snapshot_id: i64,
+    ) -> Result<Vec<(Room, Availability)>, SqliteSnapshotStoreError> {
+        let mut statement = connection.prepare_cached(
+            "SELECT room, availability FROM snapshot_rooms WHERE snapshot_id = ?1 ORDER BY position",
+        )?;
+        let rows = statement.query_map([snapshot_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut rooms = Vec::new();
+        for row in rows {
+            let (room, availability) = row?;
+            rooms.push((
+                serde_json::from_str(&room)?,
+                serde_json::from_str(&availability)?,
+            ));
+        }
+        Ok(rooms)
+    }
+}
+
+impl SnapshotStore for SqliteSnapshotStore {
+    type SaveError = SqliteSnapshotStoreError;
+    fn save(&self, snapshot: &Snapshot) -> Result<(), Self::SaveError> {
+        let mut connection = self.0.lock().unwrap();
+        let tx = connection.transaction()?;
+        tx.execute(
+            "INSERT INTO snapshots (date, group_size, scraped_at) VALUES (?1, ?2, ?3)",
+            params![snapshot.date, snapshot.group_size, snapshot.scraped_at],
+        )?;
+        let snapshot_id = tx.last_insert_rowid();
+        {
+            let mut statement = tx.prepare_cached(
+                "INSERT INTO snapshot_rooms (snapshot_id, position, title, room, availability)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (position, (room, availability)) in snapshot.rooms.iter().enumerate() {
+                statement.execute(params![
+                    snapshot_id,
+                    position,
+                    room.title(),
+                    serde_json::to_string(room)?,
+                    serde_json::to_string(availability)?,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    type LatestError = SqliteSnapshotStoreError;
+    fn latest(
+        &self,
+        date: NaiveDate,
+        group_size: Option<u8>,
+    ) -> Result<Option<Snapshot>, Self::LatestError> {
+        let connection = self.0.lock().unwrap();
+        let row = connection
+            .query_row(
+                "SELECT id, group_size, scraped_at FROM snapshots
+                 WHERE date = ?1 AND (?2 IS NULL OR group_size = ?2)
+                 ORDER BY scraped_at DESC, id DESC LIMIT 1",
+                params![date, group_size],
+                |row| Ok((row.get::<_, i64>(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        let Some((id, group_size, scraped_at)) = row else {
+            return Ok(None);
+        };
+        Ok(Some(Snapshot {
+            date,
+            group_size,
+            scraped_at,
+            rooms: Self::rooms(&connection, id)?,
+        }))
+    }
+
+    type HistoryError = SqliteSnapshotStoreError;
+    fn history(
+        &self,
+        date: NaiveDate,
+        group_size: u8,
+        limit: usize,
+    ) -> Result<Vec<Snapshot>, Self::HistoryError> {
+        let connection = self.0.lock().unwrap();
+        let mut statement = connection.prepare_cached(
+            "SELECT id, scraped_at FROM snapshots
+             WHERE date = ?1 AND group_size = ?2
+             ORDER BY scraped_at DESC, id DESC LIMIT ?3",
+        )?;
+        let rows = statement
+            .query_map(params![date, group_size, limit], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get(1)?))
+            })?
+            .collect::<Result<Vec<(i64, DateTime<Utc>)>, _>>()?;
+        let mut snapshots = Vec::with_capacity(rows.len());
+        for (id, scraped_at) in rows {
+            snapshots.push(Snapshot {
+                date,
+                group_size,
+                scraped_at,
+                rooms: Self::rooms(&connection, id)?,
+            });
+        }
+        Ok(snapshots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room::fixtures;
+
+    fn snapshot(scraped_at: &str, group_size: u8, free: &[&str]) -> Snapshot {
+        Snapshot {
+            date: NaiveDate::from_ymd_opt(2024, 3, 14).unwrap(),
+            group_size,
+            scraped_at: scraped_at.parse().unwrap(),
+            rooms: vec![fixtures::free_room("3-20A Idea Lab", free)],
+        }
+    }
+
+    #[test]
+    fn latest_snapshot_wins() {
+        let store = SqliteSnapshotStore::open_in_memory().unwrap();
+        store
+            .save(&snapshot("2024-03-01T10:00:00Z", 10, &["6:00 PM"]))
+            .unwrap();
+        store
+            .save(&snapshot(
+                "2024-03-02T10:00:00Z",
+                10,
+                &["6:00 PM", "6:30 PM"],
+            ))
+            .unwrap();
+        store
+            .save(&snapshot("2024-03-03T10:00:00Z", 20, &[]))
+            .unwrap();
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        let latest = store.latest(date, Some(10)).unwrap().unwrap();
+        assert_eq!(
+            latest.scraped_at,
+            "2024-03-02T10:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(latest.rooms[0].1.to_string(), "[6:00 PM, 6:30 PM]");
+        assert_eq!(latest.rooms[0].0.title(), "3-20A Idea Lab");
+
+        let latest_any = store.latest(date, None).unwrap().unwrap();
+        assert_eq!(latest_any.group_size, 20);
+
+        assert_eq!(store.history(date, 10, 5).unwrap().len(), 2);
+        assert!(store
+            .latest(date.succ_opt().unwrap(), None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn bookings_are_listed_in_order_and_can_be_removed() {
+        let store = SqliteSnapshotStore::open_in_memory().unwrap();
+        let room = snapshot("2024-03-01T10:00:00Z", 10, &[]).rooms.remove(0).0;
+        let booking = |id: &str, day| Booking {
+            confirmation_id: id.to_owned(),
+            room: room.clone(),
+            date: NaiveDate::from_ymd_opt(2024, 3, day).unwrap(),
+            from: "18:00:00".parse().unwrap(),
+            to: "21:00:00".parse().unwrap(),
+            group_size: 20,
+        };
+        store.record_booking(&booking("B", 21)).unwrap();
+        store.record_booking(&booking("A", 14)).unwrap();
+
+        let ids = |bookings: Vec<Booking>| {
+            bookings
+                .into_iter()
+                .map(|b| b.confirmation_id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(store.bookings(None).unwrap()), ["A", "B"]);
+        let since = NaiveDate::from_ymd_opt(2024, 3, 15);
+        assert_eq!(ids(store.bookings(since).unwrap()), ["B"]);
+
+        assert_eq!(
+            store.remove_booking("A").unwrap().unwrap().confirmation_id,
+            "A"
+        );
+        assert!(store.remove_booking("A").unwrap().is_none());
+        assert_eq!(ids(store.bookings(None).unwrap()), ["B"]);
+    }
+}
@@ -0,0 +1,69 @@
+//! The wall-clock time and the day at the library, whatever the timezone of the machine.
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Utc, Weekday};
+
+/// The timezone of the Calgary Public Library, in which every event is expressed.
+pub const TIMEZONE: &str = "America/Edmonton";
+
+/// The wall-clock time in [`TIMEZONE`] at `instant`, with the Mountain Time rules since 2007.
+pub fn library_time(instant: DateTime<Utc>) -> NaiveDateTime {
+    let sunday = |month, n| {
+        NaiveDate::from_weekday_of_month_opt(instant.year(), month, Weekday::Sun, n)
+            .expect("every month has at least four Sundays")
+    };
+    // Daylight time starts at 2 AM MST (9:00 UTC) and ends at 2 AM MDT (8:00 UTC)
+    let daylight_from = sunday(3, 2).and_hms_opt(9, 0, 0).unwrap().and_utc();
+    let daylight_to = sunday(11, 1).and_hms_opt(8, 0, 0).unwrap().and_utc();
+    let offset = if (daylight_from..daylight_to).contains(&instant) {
+        -6
+    } else {
+        -7
+    };
+    instant.naive_utc() + chrono::Duration::try_hours(offset).unwrap()
+}
+
+/// The current day in [`TIMEZONE`], whatever the timezone of the machine, e.g. a UTC container.
+pub fn library_today() -> NaiveDate {
+    library_time(Utc::now()).date()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn library_time_follows_daylight_saving_time() {
+        let utc = |s| {
+            NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+                .unwrap()
+                .and_utc()
+        };
+        let local = |s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        // A Calgary evening is already the next day in UTC
+        assert_eq!(
+            library_time(utc("2026-11-13 04:30")),
+            local("2026-11-12 21:30")
+        );
+        assert_eq!(
+            library_time(utc("2026-07-01 03:00")),
+            local("2026-06-30 21:00")
+        );
+        // 2026-03-08 and 2026-11-01 are the days of the changes
+        assert_eq!(
+            library_time(utc("2026-03-08 08:59")),
+            local("2026-03-08 01:59")
+        );
+        assert_eq!(
+            library_time(utc("2026-03-08 09:00")),
+            local("2026-03-08 03:00")
+        );
+        assert_eq!(
+            library_time(utc("2026-11-01 07:59")),
+            local("2026-11-01 01:59")
+        );
+        assert_eq!(
+            library_time(utc("2026-11-01 08:00")),
+            local("2026-11-01 01:00")
+        );
+    }
+}
@@ -0,0 +1,464 @@
+use std::{collections::HashSet, fmt::Debug, future::Future, time::Duration};
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    booking::Booking,
+    room::{Availability, Room},
+    time::library_today,
+};
+
+/// A range of the time of day, e.g. from 6 to 9 PM. It always ends after it starts,
+/// which [`TimeWindow::new`] and deserialization check.
+#[derive(Debug, Clone, Copy, Serialize, specta::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TimeWindow {
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+}
+
+#[derive(Error, Debug)]
+#[error("the time window ends at {to}, not after it starts at {from}")]
+pub struct InvalidTimeWindow {
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+}
+
+/// A condition on the available rooms, e.g. "any room for 20 people that is free
+/// from 6 to 9 PM on one of these dates".
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct WatchRule {
+    pub name: String,
+    /// The expected number of attendees. Rooms that are known to be smaller don't match.
+    pub group_size: u8,
+    /// The time for which a room must be entirely free. Any free time slot matches if unset.
+    #[serde(default)]
+    pub time_window: Option<TimeWindow>,
+    /// The days to watch.
+    pub dates: Vec<NaiveDate>,
+    /// The prefixes of the titles of the acceptable rooms, e.g. `3-20A`. Any room matches if empty.
+    #[serde(default)]
+    pub rooms: Vec<String>,
+}
+
+/// A [`WatchRule`] that has become satisfiable.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct Alert {
+    pub rule: String,
+    pub date: NaiveDate,
+    pub scraped_at: DateTime<Utc>,
+    /// The rooms that satisfy the rule.
+    pub rooms: Vec<(Room, Availability)>,
+}
+
+/// Something that the organizers should hear about.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum Notification {
+    Availability(Alert),
+    BookingConfirmed(Booking),
+}
+
+/// A way to deliver [`Notification`]s, e.g. a chat webhook or an email.
+pub trait Notifier {
+    type NotifyError: Debug;
+    async fn notify(&self, notification: &Notification) -> Result<(), Self::NotifyError>;
+}
+
+/// Prints notifications to the standard output.
+pub struct StdoutNotifier;
+
+/// When the rooms are scraped again.
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule {
+    pub interval: Duration,
+    /// Up to this much is added to every delay at random so that scrapes don't happen in lockstep.
+    pub jitter: Duration,
+    /// The longest delay between scrapes. The interval doubles after every consecutive failure.
+    pub max_backoff: Duration,
+}
+
+#[derive(Error, Debug)]
+pub enum WatchError<S: Debug, N: Debug> {
+    #[error("Failed to scrape the rooms for {date} and {group_size} people: {error:?}")]
+    Scrape {
+        date: NaiveDate,
+        group_size: u8,
+        error: S,
+    },
+    #[error("Failed to send a notification: {0:?}")]
+    Notify(N),
+}
+
+/// Re-scrapes the rooms on a [`Schedule`] and notifies when a [`WatchRule`] becomes satisfiable.
+pub struct Watcher<N> {
+    rules: Vec<WatchRule>,
+    schedule: Schedule,
+    notifier: N,
+    /// The indices of the rules together with the dates for which they were satisfiable at the last check.
+    satisfied: HashSet<(usize, NaiveDate)>,
+    consecutive_failures: u32,
+}
+
+impl TimeWindow {
+    pub fn new(from: NaiveTime, to: NaiveTime) -> Result<Self, InvalidTimeWindow> {
+        if to <= from {
+            return Err(InvalidTimeWindow { from, to });
+        }
+        Ok(Self { from, to })
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeWindow {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Unchecked {
+            from: NaiveTime,
+            to: NaiveTime,
+        }
+        let Unchecked { from, to } = Unchecked::deserialize(deserializer)?;
+        Self::new(from, to).map_err(serde::de::Error::custom)
+    }
+}
+
+impl WatchRule {
+    pub fn matches(&self, room: &Room, availability: &Availability) -> bool {
+        if !self.rooms.is_empty()
+            && !self
+                .rooms
+                .iter()
+                .any(|prefix| room.title().starts_with(prefix.as_str()))
+        {
+            return false;
+        }
+        if room
+            .capacity()
+            .is_some_and(|capacity| capacity < self.group_size)
+        {
+            return false;
+        }
+        match self.time_window {
+            Some(TimeWindow { from, to }) => availability.is_free_between(from, to),
+            None => !availability.is_empty(),
+        }
+    }
+
+    pub fn matching_rooms(&self, rooms: &[(Room, Availability)]) -> Vec<(Room, Availability)> {
+        rooms
+            .iter()
+            .filter(|(room, availability)| self.matches(room, availability))
+            .cloned()
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Notification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Notification::Availability(alert) => {
+                writeln!(f, "{:?} can be satisfied on {}:", alert.rule, alert.date)?;
+                for (room, availability) in &alert.rooms {
+                    writeln!(f, "  {}: {availability}", room.title())?;
+                }
+                Ok(())
+            }
+            Notification::BookingConfirmed(booking) => writeln!(f, "{booking}"),
+        }
+    }
+}
+
+impl Notifier for StdoutNotifier {
+    type NotifyError = std::convert::Infallible;
+    async fn notify(&self, notification: &Notification) -> Result<(), Self::NotifyError> {
+        print!("{notification}");
+        Ok(())
+    }
+}
+
+impl Schedule {
+    /// The delay before the next scrape after `consecutive_failures` failed ones.
+    pub fn delay(&self, consecutive_failures: u32) -> Duration {
+        let backoff = self
+            .interval
+            .saturating_mul(2u32.saturating_pow(consecutive_failures))
+            .min(self.max_backoff.max(self.interval));
+        let jitter = rand::thread_rng().gen_range(Duration::ZERO..=self.jitter);
+        backoff + jitter
+    }
+}
+
+impl<N: Notifier> Watcher<N> {
+    pub fn new(rules: Vec<WatchRule>, schedule: Schedule, notifier: N) -> Self {
+        Self {
+            rules,
+            schedule,
+            notifier,
+            satisfied: HashSet::new(),
+            consecutive_failures: 0,
+        }
+    }
+
+    /// The dates and group sizes that have to be scraped, starting from `today`.
+    fn queries(&self, today: NaiveDate) -> Vec<(NaiveDate, u8)> {
+        let mut queries = Vec::new();
+        for rule in &self.rules {
+            for &date in &rule.dates {
+                if date >= today && !queries.contains(&(date, rule.group_size)) {
+                    queries.push((date, rule.group_size));
+                }
+            }
+        }
+        queries
+    }
+
+    /// Scrapes every upcoming date of the rules once and notifies about
+    /// the rules that have become satisfiable since the previous check.
+    pub async fn check<F, Fut, E>(
+        &mut self,
+        today: NaiveDate,
+        scrape: &mut F,
+    ) -> Vec<WatchError<E, N::NotifyError>>
+    where
+        F: FnMut(NaiveDate, u8) -> Fut,
+        Fut: Future<Output = Result<Vec<(Room, Availability)>, E>>,
+        E: Debug,
+    {
+        let mut errors = Vec::new();
+        for (date, group_size) in self.queries(today) {
+            let rooms = match scrape(date, group_size).await {
+                Ok(rooms) => rooms,
+                Err(error) => {
+                    errors.push(WatchError::Scrape {
+                        date,
+                        group_size,
+                        error,
+                    });
+                    continue;
+                }
+            };
+            let scraped_at = Utc::now();
+            for (index, rule) in self.rules.iter().enumerate() {
+                if rule.group_size != group_size || !rule.dates.contains(&date) {
+                    continue;
+                }
+                let matching = rule.matching_rooms(&rooms);
+                if matching.is_empty() {
+                    self.satisfied.remove(&(index, date));
+                    continue;
+                }
+                if self.satisfied.contains(&(index, date)) {
+                    continue;
+                }
+                let notification = Notification::Availability(Alert {
+                    rule: rule.name.clone(),
+                    date,
+                    scraped_at,
+                    rooms: matching,
+                });
+                // Unless the notification is sent, the next check tries again
+                match self.notifier.notify(&notification).await {
+                    Ok(()) => {
+                        self.satisfied.insert((index, date));
+                    }
+                    Err(e) => errors.push(WatchError::Notify(e)),
+                }
+            }
+        }
+        if errors
+            .iter()
+            .any(|e| matches!(e, WatchError::Scrape { .. }))
+        {
+            self.consecutive_failures += 1;
+        } else {
+            self.consecutive_failures = 0;
+        }
+        errors
+    }
+
+    /// Checks the rules on the schedule until none of their dates is upcoming.
+    ///
+    /// Failures are reported to `on_error` and delay the next check.
+    pub async fn run<F, Fut, E>(
+        mut self,
+        mut scrape: F,
+        mut on_error: impl FnMut(WatchError<E, N::NotifyError>),
+    ) where
+        F: FnMut(NaiveDate, u8) -> Fut,
+        Fut: Future<Output = Result<Vec<(Room, Availability)>, E>>,
+        E: Debug,
+    {
+        loop {
+            let today = library_today();
+            if self.queries(today).is_empty() {
+                return;
+            }
+            for e in self.check(today, &mut scrape).await {
+                on_error(e);
+            }
+            tokio::time::sleep(self.schedule.delay(self.consecutive_failures)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::room::fixtures;
+
+    struct RecordingNotifier(Mutex<Vec<Notification>>);
+
+    impl Notifier for RecordingNotifier {
+        type NotifyError = std::convert::Infallible;
+        async fn notify(&self, notification: &Notification) -> Result<(), Self::NotifyError> {
+            self.0.lock().unwrap().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    fn idea_lab(free: &[&str]) -> Vec<(Room, Availability)> {
+        vec![fixtures::free_room("3-20A Idea Lab", free)]
+    }
+
+    fn evening_rule(date: NaiveDate) -> WatchRule {
+        WatchRule {
+            name: "Meetup".to_owned(),
+            group_size: 20,
+            time_window: Some(
+                TimeWindow::new(
+                    NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+                    NaiveTime::from_hms_opt(19, 0, 0).unwrap(),
+                )
+                .unwrap(),
+            ),
+            dates: vec![date],
+            rooms: vec![],
+        }
+    }
+
+    const SCHEDULE: Schedule = Schedule {
+        interval: Duration::from_secs(60),
+        jitter: Duration::from_secs(10),
+        max_backoff: Duration::from_secs(600),
+    };
+
+    #[test]
+    fn the_whole_time_window_must_be_free() {
+        let rule = evening_rule(NaiveDate::from_ymd_opt(2024, 3, 14).unwrap());
+        let [(room, availability)] = &idea_lab(&["6:00 PM"])[..] else {
+            unreachable!()
+        };
+        assert!(!rule.matches(room, availability));
+        let [(room, availability)] = &idea_lab(&["5:30 PM", "6:00 PM", "6:30 PM"])[..] else {
+            unreachable!()
+        };
+        assert!(rule.matches(room, availability));
+        assert!(!WatchRule {
+            group_size: 30,
+            ..rule
+        }
+        .matches(room, availability));
+    }
+
+    #[test]
+    fn inverted_time_windows_are_rejected() {
+        let window = serde_json::json!({ "from": "21:00:00", "to": "18:00:00" });
+        assert!(serde_json::from_value::<TimeWindow>(window).is_err());
+        let window = serde_json::json!({ "from": "18:00:00", "to": "21:00:00" });
+        assert!(serde_json::from_value::<TimeWindow>(window).is_ok());
+    }
+
+    #[tokio::test]
+    async fn notifies_once_when_a_rule_becomes_satisfiable() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        let mut watcher = Watcher::new(
+            vec![evening_rule(date)],
+            SCHEDULE,
+            RecordingNotifier(Mutex::new(Vec::new())),
+        );
+        let mut results = vec![
+            idea_lab(&["6:00 PM", "6:30 PM"]),
+            idea_lab(&["6:00 PM", "6:30 PM"]),
+            idea_lab(&["6:00 PM"]),
+        ]
+        .into_iter();
+        let mut scrape = |_, _| {
+            let rooms = results.next().unwrap();
+            async move { Ok::<_, ()>(rooms) }
+        };
+
+        for _ in 0..3 {
+            assert!(watcher.check(today, &mut scrape).await.is_empty());
+        }
+        let notifications = watcher.notifier.0.lock().unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert!(watcher.satisfied.is_empty());
+    }
+
+    /// Fails to send the first `failures` notifications, then records the others.
+    struct FlakyNotifier {
+        failures: Mutex<usize>,
+        sent: Mutex<Vec<Notification>>,
+    }
+
+    impl Notifier for FlakyNotifier {
+        type NotifyError = &'static str;
+        async fn notify(&self, notification: &Notification) -> Result<(), Self::NotifyError> {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err("the webhook is down");
+            }
+            self.sent.lock().unwrap().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_notifications_are_sent_again() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        let mut watcher = Watcher::new(
+            vec![evening_rule(date)],
+            SCHEDULE,
+            FlakyNotifier {
+                failures: Mutex::new(1),
+                sent: Mutex::new(Vec::new()),
+            },
+        );
+        let mut scrape = |_, _| async { Ok::<_, ()>(idea_lab(&["6:00 PM", "6:30 PM"])) };
+
+        let errors = watcher.check(today, &mut scrape).await;
+        assert!(matches!(errors[..], [WatchError::Notify(_)]));
+        assert!(watcher.check(today, &mut scrape).await.is_empty());
+        assert!(watcher.check(today, &mut scrape).await.is_empty());
+        assert_eq!(watcher.notifier.sent.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn past_dates_are_not_scraped() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        let mut watcher = Watcher::new(vec![evening_rule(date)], SCHEDULE, StdoutNotifier);
+        let mut scrape = |_, _| async { Err::<Vec<_>, _>("unreachable") };
+        let errors = watcher.check(date.succ_opt().unwrap(), &mut scrape).await;
+        assert!(errors.is_empty());
+        let errors = watcher.check(date, &mut scrape).await;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(watcher.consecutive_failures, 1);
+    }
+
+    #[test]
+    fn failures_back_off_up_to_the_limit() {
+        let delay = SCHEDULE.delay(0);
+        assert!(delay >= SCHEDULE.interval && delay <= SCHEDULE.interval + SCHEDULE.jitter);
+        let delay = SCHEDULE.delay(2);
+        assert!(delay >= SCHEDULE.interval * 4);
+        let delay = SCHEDULE.delay(100);
+        assert!(delay <= SCHEDULE.max_backoff + SCHEDULE.jitter);
+    }
+}
@@ -0,0 +1,257 @@
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use reqwest::{header::CONTENT_TYPE, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::watch::{Notification, Notifier};
+
+/// The header with the hex-encoded HMAC-SHA256 of the body, prefixed with `sha256=`.
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+
+/// Discord rejects messages that are longer than this.
+const DISCORD_MAX_CONTENT_LEN: usize = 2000;
+
+/// The shape of the JSON body that is POSTed to a webhook.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// `{"content": "..."}` for Discord webhooks.
+    Discord,
+    /// `{"text": "..."}` for Slack incoming webhooks.
+    Slack,
+    /// The [`Notification`] itself, e.g. `{"availability": {...}}`.
+    #[default]
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// The key of the signature in the [`SIGNATURE_HEADER`]. The requests are not signed if unset.
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// How many times a request is repeated after a network error, a 5XX or a 429 response.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+}
+
+/// POSTs notifications to a webhook.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    config: WebhookConfig,
+    /// The delay before the first retry. It doubles with every next one.
+    retry_delay: Duration,
+}
+
+#[derive(Error, Debug)]
+pub enum WebhookError {
+    #[error("Failed to send the webhook request: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("The webhook responded with {0}")]
+    Status(StatusCode),
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+impl WebhookNotifier {
+    pub fn new(config: WebhookConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+
+    pub fn with_retry_delay(self, retry_delay: Duration) -> Self {
+        Self {
+            retry_delay,
+            ..self
+        }
+    }
+
+    /// The JSON body of the request for `notification`.
+    pub fn body(&self, notification: &Notification) -> serde_json::Value {
+        match self.config.format {
+            WebhookFormat::Discord => {
+                let content: String = notification
+                    .to_string()
+                    .chars()
+                    .take(DISCORD_MAX_CONTENT_LEN)
+                    .collect();
+                json!({ "content": content })
+            }
+            WebhookFormat::Slack => json!({ "text": notification.to_string() }),
+            WebhookFormat::Json => {
+                serde_json::to_value(notification).expect("notifications are always serializable")
+            }
+        }
+    }
+
+    fn signature(&self, body: &[u8]) -> Option<String> {
+        let secret = self.config.secret.as_ref()?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(body);
+        Some(format!(
+            "sha256={}",
+            hex::encode(mac.finalize().into_bytes())
+        ))
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    type NotifyError = WebhookError;
+
+    async fn notify(&self, notification: &Notification) -> Result<(), Self::NotifyError> {
+        let body = self.body(notification).to_string();
+        let signature = self.signature(body.as_bytes());
+        let mut attempt = 0;
+        loop {
+            let mut request = self
+                .client
+                .post(&self.config.url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            if let Some(signature) = &signature {
+                request = request.header(SIGNATURE_HEADER, signature);
+            }
+            let e = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    if !status.is_server_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                        return Err(WebhookError::Status(status));
+                    }
+                    WebhookError::Status(status)
+                }
+                Err(e) => WebhookError::Request(e),
+            };
+            if attempt >= self.config.retries {
+                return Err(e);
+            }
+            tokio::time::sleep(self.retry_delay * 2u32.saturating_pow(attempt)).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    use axum::{extract::State, http::HeaderMap, routing::post, Router};
+    use chrono::{NaiveDate, NaiveTime};
+
+    use super::*;
+    use crate::{booking::Booking, room::fixtures};
+
+    #[derive(Clone, Default)]
+    struct StandIn {
+        requests: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+        /// The responses that are going to be failures, from the first one.
+        failures: Arc<AtomicUsize>,
+        failure_status: u16,
+    }
+
+    async fn hook(
+        State(stand_in): State<StandIn>,
+        headers: HeaderMap,
+        body: String,
+    ) -> axum::http::StatusCode {
+        stand_in.requests.lock().unwrap().push((headers, body));
+        let failures = stand_in.failures.load(Ordering::SeqCst);
+        if failures > 0 {
+            stand_in.failures.store(failures - 1, Ordering::SeqCst);
+            return axum::http::StatusCode::from_u16(stand_in.failure_status).unwrap();
+        }
+        axum::http::StatusCode::NO_CONTENT
+    }
+
+    /// Serves the stand-in on a random local port and returns the URL of its hook.
+    async fn serve(stand_in: StandIn) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/hook", post(hook))
+            .with_state(stand_in);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}/hook")
+    }
+
+    fn notifier(url: String, format: WebhookFormat) -> WebhookNotifier {
+        WebhookNotifier::new(WebhookConfig {
+            url,
+            format,
+            secret: Some("hunter2".to_owned()),
+            retries: 2,
+        })
+        .with_retry_delay(Duration::from_millis(1))
+    }
+
+    fn booking_confirmed() -> Notification {
+        Notification::BookingConfirmed(Booking {
+            confirmation_id: "ABC123".to_owned(),
+            room: fixtures::room("3-20A Idea Lab"),
+            date: NaiveDate::from_ymd_opt(2024, 3, 14).unwrap(),
+            from: NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+            to: NaiveTime::from_hms_opt(21, 0, 0).unwrap(),
+            group_size: 20,
+        })
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried_and_requests_are_signed() {
+        let stand_in = StandIn {
+            failures: Arc::new(AtomicUsize::new(2)),
+            failure_status: 503,
+            ..Default::default()
+        };
+        let url = serve(stand_in.clone()).await;
+        let notifier = notifier(url, WebhookFormat::Json);
+        notifier.notify(&booking_confirmed()).await.unwrap();
+
+        let requests = stand_in.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        let (headers, body) = &requests[2];
+        let json: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(json["booking_confirmed"]["confirmation_id"], "ABC123");
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"hunter2").unwrap();
+        mac.update(body.as_bytes());
+        let expected = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        assert_eq!(headers[SIGNATURE_HEADER], expected.as_str());
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let stand_in = StandIn {
+            failures: Arc::new(AtomicUsize::new(1)),
+            failure_status: 400,
+            ..Default::default()
+        };
+        let url = serve(stand_in.clone()).await;
+        let notifier = notifier(url, WebhookFormat::Slack);
+        let e = notifier.notify(&booking_confirmed()).await.unwrap_err();
+        assert!(matches!(e, WebhookError::Status(StatusCode::BAD_REQUEST)));
+        assert_eq!(stand_in.requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn chat_bodies_carry_the_text() {
+        let notifier = notifier(String::new(), WebhookFormat::Discord);
+        let body = notifier.body(&booking_confirmed());
+        let content = body["content"].as_str().unwrap();
+        assert!(content.starts_with("3-20A Idea Lab is booked on 2024-03-14 from 6:00 PM"));
+    }
+}
@@ -0,0 +1,496 @@
+use std::convert::Infallible;
+
+use axum::{
+    debug_handler,
+    extract::State,
+    http::{header, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    Json,
+};
+use calgary_central_library::{
+    library_today, Availability, Calendar, Room, Snapshot, SnapshotStore,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tokio::{sync::mpsc, task::AbortHandle};
+use tokio_stream::{wrappers::UnboundedReceiverStream, Stream, StreamExt};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    cache::{Freshness, RoomsResponse, Step, Watched},
+    error::{ApiError, ApiJson, ApiPath, ApiQuery, ErrorBody, ErrorCode},
+    feeds::{self, FeedProfile, FeedView},
+    health::{self, Readiness},
+    jobs::{JobStatus, JobView, Progress},
+    CCLSHBServerState,
+};
+
+/// The `location` identifier that the library website uses for the Central Library.
+pub(crate) const CENTRAL_LIBRARY_BRANCH_ID: u32 = 1;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Calgary Central Library rooms"),
+    paths(
+        health::healthz,
+        health::readyz,
+        branches,
+        rooms,
+        rooms_stream,
+        rooms_ics,
+        latest_rooms,
+        feeds::feeds,
+        feeds::feed,
+        create_job,
+        job,
+        cancel_job
+    ),
+    components(schemas(
+        Branch,
+        Room,
+        Availability,
+        Snapshot,
+        FeedProfile,
+        FeedView,
+        NewJob,
+        JobView,
+        JobStatus,
+        Progress,
+        ErrorBody,
+        ErrorCode,
+        Readiness,
+        RoomsResponse,
+        Freshness
+    ))
+)]
+pub(crate) struct ApiDoc;
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct Branch {
+    id: u32,
+    name: &'static str,
+}
+
+#[derive(Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct RoomsQuery {
+    /// The day to check, in the `YYYY-MM-DD` format.
+    pub(crate) date: NaiveDate,
+    /// The expected number of attendees. The group size of the default profile
+    /// of the shared configuration by default.
+    pub(crate) group_size: Option<u8>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct NewJob {
+    /// The identifier of the branch. The Central Library by default.
+    #[serde(default = "central_library_branch_id")]
+    branch_id: u32,
+    date: NaiveDate,
+    /// The group size of the default profile of the shared configuration by default.
+    group_size: Option<u8>,
+}
+
+fn central_library_branch_id() -> u32 {
+    CENTRAL_LIBRARY_BRANCH_ID
+}
+
+fn validate_branch(branch_id: u32) -> Result<(), ApiError> {
+    if branch_id != CENTRAL_LIBRARY_BRANCH_ID {
+        return Err(ApiError::unknown_branch(branch_id));
+    }
+    Ok(())
+}
+
+fn validate_query(date: NaiveDate, group_size: u8) -> Result<(), ApiError> {
+    let today = library_today();
+    if date < today {
+        return Err(ApiError::new(
+            ErrorCode::InvalidDate,
+            format!("The date {date} is in the past"),
+        ));
+    }
+    if group_size == 0 {
+        return Err(ApiError::new(
+            ErrorCode::InvalidGroupSize,
+            "The group size must be at least 1",
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Lists the library branches whose rooms can be queried.
+#[utoipa::path(get, path = "/branches", responses((status = 200, body = Vec<Branch>)))]
+pub(crate) async fn branches() -> Json<Vec<Branch>> {
+    Json(vec![Branch {
+        id: CENTRAL_LIBRARY_BRANCH_ID,
+        name: "Central Library",
+    }])
+}
+
+/// Lists the rooms of a branch together with their free time slots on the given day.
+///
+/// The rooms may come from the cache, which is reflected by `scraped_at` and `freshness`.
+#[utoipa::path(
+    get,
+    path = "/branches/{id}/rooms",
+    params(("id" = u32, Path, description = "The identifier of the branch"), RoomsQuery),
+    responses(
+        (status = 200, body = RoomsResponse),
+        (status = "4XX", body = ErrorBody),
+        (status = "5XX", body = ErrorBody),
+    )
+)]
+#[debug_handler]
+pub(crate) async fn rooms(
+    State(state): State<CCLSHBServerState>,
+    ApiPath(branch_id): ApiPath<u32>,
+    ApiQuery(query): ApiQuery<RoomsQuery>,
+) -> Result<Json<RoomsResponse>, ApiError> {
+    validate_branch(branch_id)?;
+    let RoomsQuery { date, group_size } = query;
+    let group_size = group_size.unwrap_or(state.default_group_size);
+    validate_query(date, group_size)?;
+    let response = state.cache.get((branch_id, date, group_size)).await?;
+    Ok(Json(response))
+}
+
+/// Returns the same rooms as `GET /branches/{id}/rooms` as an iCalendar file
+/// with the free time of every room as tentative events in the America/Edmonton timezone.
+#[utoipa::path(
+    get,
+    path = "/branches/{id}/rooms.ics",
+    params(("id" = u32, Path, description = "The identifier of the branch"), RoomsQuery),
+    responses(
+        (status = 200, content_type = "text/calendar", body = String),
+        (status = "4XX", body = ErrorBody),
+        (status = "5XX", body = ErrorBody),
+    )
+)]
+pub(crate) async fn rooms_ics(
+    State(state): State<CCLSHBServerState>,
+    ApiPath(branch_id): ApiPath<u32>,
+    ApiQuery(query): ApiQuery<RoomsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    validate_branch(branch_id)?;
+    let RoomsQuery { date, group_size } = query;
+    let group_size = group_size.unwrap_or(state.default_group_size);
+    validate_query(date, group_size)?;
+    let response = state.cache.get((branch_id, date, group_size)).await?;
+    let snapshot = Snapshot {
+        date,
+        group_size,
+        scraped_at: response.scraped_at,
+        rooms: response.rooms.to_vec(),
+    };
+    let name = format!("Free rooms for {group_size} people on {date}");
+    let ics = Calendar::new(name).add_free_windows(&snapshot).to_string();
+    Ok((
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        ics,
+    ))
+}
+
+/// Lists the same rooms as `GET /branches/{id}/rooms` but sends each of them
+/// as a Server-Sent Event as soon as it is parsed.
+///
+/// The stream consists of a `started` event with the total number of rooms,
+/// a `room` event per room and either a `done` or an `error` event at the end.
+/// Like `GET /branches/{id}/rooms`, it shares the scrape with the identical requests
+/// in progress, and the rooms that are cached are sent all at once.
+#[utoipa::path(
+    get,
+    path = "/branches/{id}/rooms/stream",
+    params(("id" = u32, Path, description = "The identifier of the branch"), RoomsQuery),
+    responses(
+        (status = 200, content_type = "text/event-stream", description = "Server-Sent Events"),
+        (status = "4XX", body = ErrorBody),
+    )
+)]
+pub(crate) async fn rooms_stream(
+    State(state): State<CCLSHBServerState>,
+    ApiPath(branch_id): ApiPath<u32>,
+    ApiQuery(query): ApiQuery<RoomsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    validate_branch(branch_id)?;
+    let RoomsQuery { date, group_size } = query;
+    let group_size = group_size.unwrap_or(state.default_group_size);
+    validate_query(date, group_size)?;
+    let (tx, rx) = mpsc::unbounded_channel::<Event>();
+    let send = move |event: Result<Event, axum::Error>| {
+        let _ = tx.send(event.expect("rooms are always serializable"));
+    };
+    let watched = state.cache.watch((branch_id, date, group_size));
+    let forward = tokio::spawn(async move {
+        let result = match watched {
+            Watched::Cached(response) => {
+                send(started_event(response.rooms.len()));
+                for (room, availability) in response.rooms.iter() {
+                    send(room_event(room, availability));
+                }
+                Ok(response)
+            }
+            Watched::Scraping(scrape) => {
+                scrape
+                    .follow(|step| match step {
+                        Step::PageLoaded { total } => send(started_event(total)),
+                        Step::RoomParsed {
+                            room, availability, ..
+                        } => send(room_event(&room, &availability)),
+                    })
+                    .await
+            }
+        };
+        send(match result {
+            Ok(response) => Event::default()
+                .event("done")
+                .json_data(serde_json::json!({ "rooms_total": response.rooms.len() })),
+            Err(e) => Event::default().event("error").json_data(e.body),
+        });
+    });
+
+    // The events stop as soon as the client goes away, but the scrape still fills the cache
+    let abort_on_drop = AbortOnDrop(forward.abort_handle());
+    let stream = UnboundedReceiverStream::new(rx).map(move |event| {
+        let _ = &abort_on_drop;
+        Ok(event)
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Returns the most recently recorded scrape for the query, which may be in the past.
+///
+/// It lets clients show the last known rooms when the library website or WebDriver is down.
+#[utoipa::path(
+    get,
+    path = "/branches/{id}/rooms/latest",
+    params(("id" = u32, Path, description = "The identifier of the branch"), RoomsQuery),
+    responses(
+        (status = 200, body = Snapshot),
+        (status = 404, body = ErrorBody, description = "No scrape has been recorded"),
+        (status = "4XX", body = ErrorBody),
+        (status = "5XX", body = ErrorBody),
+    )
+)]
+pub(crate) async fn latest_rooms(
+    State(state): State<CCLSHBServerState>,
+    ApiPath(branch_id): ApiPath<u32>,
+    ApiQuery(query): ApiQuery<RoomsQuery>,
+) -> Result<Json<Snapshot>, ApiError> {
+    validate_branch(branch_id)?;
+    let RoomsQuery { date, group_size } = query;
+    let group_size = group_size.unwrap_or(state.default_group_size);
+    let Some(snapshots) = state.scraper.snapshots.clone() else {
+        return Err(ApiError::no_snapshot());
+    };
+    let latest = tokio::task::spawn_blocking(move || snapshots.latest(date, Some(group_size)))
+        .await
+        .expect("reading a snapshot doesn't panic")
+        .map_err(|e| ApiError::new(ErrorCode::SnapshotStoreFailed, e.to_string()))?;
+    latest.map(Json).ok_or_else(ApiError::no_snapshot)
+}
+
+fn started_event(rooms_total: usize) -> Result<Event, axum::Error> {
+    Event::default()
+        .event("started")
+        .json_data(serde_json::json!({ "rooms_total": rooms_total }))
+}
+
+fn room_event(room: &Room, availability: &Availability) -> Result<Event, axum::Error> {
+    Event::default()
+        .event("room")
+        .json_data((room, availability))
+}
+
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Enqueues a scrape. Its progress and results can be polled with `GET /jobs/{id}`.
+#[utoipa::path(
+    post,
+    path = "/jobs",
+    request_body = NewJob,
+    responses(
+        (status = 202, body = JobView),
+        (status = "4XX", body = ErrorBody),
+    )
+)]
+pub(crate) async fn create_job(
+    State(state): State<CCLSHBServerState>,
+    ApiJson(new_job): ApiJson<NewJob>,
+) -> Result<impl IntoResponse, ApiError> {
+    let NewJob {
+        branch_id,
+        date,
+        group_size,
+    } = new_job;
+    validate_branch(branch_id)?;
+    let group_size = group_size.unwrap_or(state.default_group_size);
+    validate_query(date, group_size)?;
+    let job = state
+        .jobs
+        .start(&state.cache, (branch_id, date, group_size));
+    let location = format!("/jobs/{}", job.id);
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(job),
+    ))
+}
+
+/// Reports the progress of a scrape job and, once it has succeeded, its results.
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}",
+    params(("id" = Uuid, Path, description = "The identifier of the job")),
+    responses(
+        (status = 200, body = JobView),
+        (status = 404, body = ErrorBody, description = "Unknown or expired job"),
+    )
+)]
+pub(crate) async fn job(
+    State(state): State<CCLSHBServerState>,
+    ApiPath(id): ApiPath<Uuid>,
+) -> Result<Json<JobView>, ApiError> {
+    state
+        .jobs
+        .get(id)
+        .map(Json)
+        .ok_or_else(ApiError::unknown_job)
+}
+
+/// Cancels a queued or running scrape job.
+#[utoipa::path(
+    delete,
+    path = "/jobs/{id}",
+    params(("id" = Uuid, Path, description = "The identifier of the job")),
+    responses(
+        (status = 200, body = JobView),
+        (status = 404, body = ErrorBody, description = "Unknown or expired job"),
+    )
+)]
+pub(crate) async fn cancel_job(
+    State(state): State<CCLSHBServerState>,
+    ApiPath(id): ApiPath<Uuid>,
+) -> Result<Json<JobView>, ApiError> {
+    state
+        .jobs
+        .cancel(id)
+        .map(Json)
+        .ok_or_else(ApiError::unknown_job)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use axum::{routing::get, Router};
+    use calgary_central_library::{fixtures, ScrapeProgress};
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::{
+        cache::{Fetch, RoomsCache, Steps},
+        health::ReadinessProbe,
+        jobs::JobRegistry,
+        pool::{ClientPool, PoolConfig},
+        scrape::Scraper,
+    };
+
+    /// A cache whose scrapes parse two rooms, or fail after the first one if `fail`.
+    fn cache(fail: bool) -> Arc<RoomsCache> {
+        let fetch: Fetch = Arc::new(move |_, steps: Arc<Steps>| {
+            async move {
+                let rooms = vec![
+                    fixtures::free_room("3-20A Idea Lab", &["6:00 PM"]),
+                    fixtures::free_room("3-10A Meeting Room", &[]),
+                ];
+                steps.report(ScrapeProgress::PageLoaded { total: 2 });
+                for (index, (room, availability)) in rooms.iter().enumerate() {
+                    if fail && index == 1 {
+                        return Err(ApiError::new(ErrorCode::LibrarySiteChanged, "No rooms"));
+                    }
+                    steps.report(ScrapeProgress::RoomParsed {
+                        index,
+                        total: 2,
+                        room,
+                        availability,
+                    });
+                }
+                Ok(rooms)
+            }
+            .boxed()
+        });
+        RoomsCache::new(Duration::from_secs(60), Duration::from_secs(60), fetch)
+    }
+
+    /// Serves the stream of the rooms on a random local port and returns its URL.
+    async fn serve(cache: Arc<RoomsCache>) -> String {
+        // The WebDriver servers are never contacted, since the cache doesn't use the scraper
+        let scraper = Arc::new(Scraper {
+            pool: ClientPool::new(PoolConfig {
+                web_drivers: vec!["localhost:4444".parse().unwrap()],
+                max_size: 1,
+                max_uses: 1,
+                acquire_timeout: Duration::from_secs(1),
+                health_check_timeout: Duration::from_secs(1),
+            }),
+            timeout: Duration::from_secs(1),
+            snapshots: None,
+        });
+        let state = CCLSHBServerState {
+            readiness: Arc::new(ReadinessProbe::new(scraper.clone())),
+            scraper,
+            cache,
+            jobs: JobRegistry::new(Duration::from_secs(60)),
+            feeds: Arc::default(),
+            default_group_size: 10,
+        };
+        let app = Router::new()
+            .route("/branches/:id/rooms/stream", get(rooms_stream))
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!(
+            "http://{addr}/branches/1/rooms/stream?date={}",
+            library_today()
+        )
+    }
+
+    /// The names of the events of the whole stream.
+    async fn events(url: &str) -> Vec<String> {
+        let body = reqwest::get(url).await.unwrap().text().await.unwrap();
+        body.lines()
+            .filter_map(|line| line.strip_prefix("event: "))
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn rooms_are_streamed_between_started_and_done() {
+        let url = serve(cache(false)).await;
+        assert_eq!(events(&url).await, ["started", "room", "room", "done"]);
+        // The second time, from the cache
+        assert_eq!(events(&url).await, ["started", "room", "room", "done"]);
+    }
+
+    #[tokio::test]
+    async fn failed_scrapes_end_the_stream_with_an_error() {
+        let url = serve(cache(true)).await;
+        assert_eq!(events(&url).await, ["started", "room", "error"]);
+    }
+}
@@ -0,0 +1,396 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use calgary_central_library::{Availability, Room, ScrapeProgress};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::future::{BoxFuture, FutureExt, Shared};
+use serde::Serialize;
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::Instant,
+};
+use utoipa::ToSchema;
+
+use crate::error::{ApiError, ErrorCode};
+
+/// The branch, the date and the group size of a query.
+pub(crate) type CacheKey = (u32, NaiveDate, u8);
+
+/// Scrapes the rooms of a query, reporting every step to the [`Steps`].
+pub(crate) type Fetch = Arc<
+    dyn Fn(CacheKey, Arc<Steps>) -> BoxFuture<'static, Result<Vec<(Room, Availability)>, ApiError>>
+        + Send
+        + Sync,
+>;
+
+type Inflight = Shared<BoxFuture<'static, Result<Arc<Entry>, ApiError>>>;
+
+/// A [`ScrapeProgress`] that is owned, so that every request that waits for the scrape gets it.
+#[derive(Clone, Debug)]
+pub(crate) enum Step {
+    PageLoaded {
+        total: usize,
+    },
+    RoomParsed {
+        index: usize,
+        total: usize,
+        room: Room,
+        availability: Availability,
+    },
+}
+
+/// The steps of a scrape in progress. The requests that join the scrape late get
+/// the earlier steps first.
+#[derive(Default)]
+pub(crate) struct Steps(Mutex<StepsState>);
+
+#[derive(Default)]
+struct StepsState {
+    steps: Vec<Step>,
+    subscribers: Vec<UnboundedSender<Step>>,
+}
+
+/// A scrape that is in progress, shared by the identical requests.
+#[derive(Clone)]
+struct InflightScrape {
+    result: Inflight,
+    steps: Arc<Steps>,
+}
+
+/// The rooms of a query, either from the cache or from the scrape that is in progress.
+pub(crate) enum Watched {
+    Cached(RoomsResponse),
+    Scraping(ScrapeWatch),
+}
+
+/// A scrape in progress, with its steps from the start.
+pub(crate) struct ScrapeWatch {
+    steps: UnboundedReceiver<Step>,
+    result: Inflight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Freshness {
+    /// The rooms were scraped less than the cache TTL ago.
+    Fresh,
+    /// The rooms are older than the cache TTL and are being scraped again in the background.
+    Stale,
+}
+
+/// The available rooms together with the metadata of the cache.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct RoomsResponse {
+    pub(crate) scraped_at: DateTime<Utc>,
+    pub(crate) freshness: Freshness,
+    #[schema(value_type = Vec<(Room, Availability)>)]
+    pub(crate) rooms: Arc<Vec<(Room, Availability)>>,
+}
+
+struct Entry {
+    rooms: Arc<Vec<(Room, Availability)>>,
+    scraped_at: DateTime<Utc>,
+    fetched_at: Instant,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, Arc<Entry>>,
+    inflight: HashMap<CacheKey, InflightScrape>,
+}
+
+/// An in-memory cache of scrape results that deduplicates identical in-flight scrapes.
+///
+/// Results younger than `ttl` are served as they are. Results younger than `ttl + stale_ttl`
+/// are served as stale while a scrape refreshes them in the background.
+pub(crate) struct RoomsCache {
+    ttl: Duration,
+    stale_ttl: Duration,
+    fetch: Fetch,
+    state: Mutex<CacheState>,
+}
+
+impl Entry {
+    fn response(&self, freshness: Freshness) -> RoomsResponse {
+        RoomsResponse {
+            scraped_at: self.scraped_at,
+            freshness,
+            rooms: self.rooms.clone(),
+        }
+    }
+}
+
+impl From<ScrapeProgress<'_>> for Step {
+    fn from(progress: ScrapeProgress<'_>) -> Self {
+        match progress {
+            ScrapeProgress::PageLoaded { total } => Step::PageLoaded { total },
+            ScrapeProgress::RoomParsed {
+                index,
+                total,
+                room,
+                availability,
+            } => Step::RoomParsed {
+                index,
+                total,
+                room: room.clone(),
+                availability: availability.clone(),
+            },
+        }
+    }
+}
+
+impl Steps {
+    pub(crate) fn report(&self, progress: ScrapeProgress<'_>) {
+        let step = Step::from(progress);
+        let mut state = self.0.lock().unwrap();
+        // The requests that went away are forgotten
+        state
+            .subscribers
+            .retain(|subscriber| subscriber.send(step.clone()).is_ok());
+        state.steps.push(step);
+    }
+
+    fn subscribe(&self) -> UnboundedReceiver<Step> {
+        let (subscriber, steps) = mpsc::unbounded_channel();
+        let mut state = self.0.lock().unwrap();
+        for step in &state.steps {
+            let _ = subscriber.send(step.clone());
+        }
+        state.subscribers.push(subscriber);
+        steps
+    }
+}
+
+impl ScrapeWatch {
+    /// Passes every step to `on_step` until the scrape ends, then returns its result.
+    pub(crate) async fn follow(
+        self,
+        mut on_step: impl FnMut(Step),
+    ) -> Result<RoomsResponse, ApiError> {
+        let Self {
+            mut steps,
+            mut result,
+        } = self;
+        let entry = loop {
+            tokio::select! {
+                biased;
+                Some(step) = steps.recv() => on_step(step),
+                entry = &mut result => break entry?,
+            }
+        };
+        // The steps that were reported right before the end
+        while let Ok(step) = steps.try_recv() {
+            on_step(step);
+        }
+        Ok(entry.response(Freshness::Fresh))
+    }
+}
+
+impl RoomsCache {
+    pub(crate) fn new(ttl: Duration, stale_ttl: Duration, fetch: Fetch) -> Arc<Self> {
+        Arc::new(Self {
+            ttl,
+            stale_ttl,
+            fetch,
+            state: Mutex::new(CacheState::default()),
+        })
+    }
+
+    pub(crate) async fn get(self: &Arc<Self>, key: CacheKey) -> Result<RoomsResponse, ApiError> {
+        match self.watch(key) {
+            Watched::Cached(response) => Ok(response),
+            Watched::Scraping(scrape) => scrape.follow(|_| {}).await,
+        }
+    }
+
+    /// Like [`RoomsCache::get`], with the steps of the scrape if the rooms have to wait for one.
+    pub(crate) fn watch(self: &Arc<Self>, key: CacheKey) -> Watched {
+        let mut state = self.state.lock().unwrap();
+        let inflight = match state.entries.get(&key).cloned() {
+            Some(entry) if entry.fetched_at.elapsed() < self.ttl => {
+                return Watched::Cached(entry.response(Freshness::Fresh));
+            }
+            Some(entry) if entry.fetched_at.elapsed() < self.ttl + self.stale_ttl => {
+                // The scrape runs in its own task, so nothing needs to wait for it here
+                drop(self.refresh(&mut state, key));
+                return Watched::Cached(entry.response(Freshness::Stale));
+            }
+            _ => self.refresh(&mut state, key),
+        };
+        Watched::Scraping(ScrapeWatch {
+            steps: inflight.steps.subscribe(),
+            result: inflight.result,
+        })
+    }
+
+    /// The cached rooms for `key`, without scraping them if they are missing or old.
+    pub(crate) fn peek(&self, key: CacheKey) -> Option<RoomsResponse> {
+        let state = self.state.lock().unwrap();
+        let entry = state.entries.get(&key)?;
+        let age = entry.fetched_at.elapsed();
+        if age < self.ttl {
+            Some(entry.response(Freshness::Fresh))
+        } else if age < self.ttl + self.stale_ttl {
+            Some(entry.response(Freshness::Stale))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the scrape of `key` that is in progress or starts a new one.
+    fn refresh(self: &Arc<Self>, state: &mut CacheState, key: CacheKey) -> InflightScrape {
+        if let Some(inflight) = state.inflight.get(&key) {
+            return inflight.clone();
+        }
+        let cache = self.clone();
+        let steps = Arc::new(Steps::default());
+        // The scrape runs in its own task so that it completes even if every requester goes away
+        let handle = tokio::spawn({
+            let steps = steps.clone();
+            async move {
+                let result = (cache.fetch)(key, steps).await;
+                let mut state = cache.state.lock().unwrap();
+                state.inflight.remove(&key);
+                let rooms = result?;
+                let entry = Arc::new(Entry {
+                    rooms: Arc::new(rooms),
+                    scraped_at: Utc::now(),
+                    fetched_at: Instant::now(),
+                });
+                state.entries.insert(key, entry.clone());
+                Ok(entry)
+            }
+        });
+        let result = async move {
+            handle.await.unwrap_or_else(|e| {
+                Err(ApiError::new(
+                    ErrorCode::ScrapeFailed,
+                    format!("The scrape was interrupted: {e}"),
+                ))
+            })
+        }
+        .boxed()
+        .shared();
+        let inflight = InflightScrape { result, steps };
+        state.inflight.insert(key, inflight.clone());
+        inflight
+    }
+
+    /// Forgets the results that are too old to be served even as stale.
+    pub(crate) fn remove_expired(&self) {
+        let max_age = self.ttl + self.stale_ttl;
+        self.state
+            .lock()
+            .unwrap()
+            .entries
+            .retain(|_, entry| entry.fetched_at.elapsed() < max_age);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+    const STALE_TTL: Duration = Duration::from_secs(600);
+
+    fn key() -> CacheKey {
+        (1, NaiveDate::from_ymd_opt(2024, 3, 14).unwrap(), 10)
+    }
+
+    /// A cache whose every scrape loads the page, then takes a second and succeeds with no rooms.
+    fn counting_cache() -> (Arc<RoomsCache>, Arc<AtomicUsize>) {
+        let scrapes = Arc::new(AtomicUsize::new(0));
+        let fetch: Fetch = {
+            let scrapes = scrapes.clone();
+            Arc::new(move |_, steps: Arc<Steps>| {
+                scrapes.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    steps.report(ScrapeProgress::PageLoaded { total: 0 });
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    Ok(Vec::new())
+                }
+                .boxed()
+            })
+        };
+        (RoomsCache::new(TTL, STALE_TTL, fetch), scrapes)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn identical_requests_share_one_scrape() {
+        let (cache, scrapes) = counting_cache();
+        let (a, b) = tokio::join!(cache.get(key()), cache.get(key()));
+        assert_eq!(a.unwrap().freshness, Freshness::Fresh);
+        assert_eq!(b.unwrap().freshness, Freshness::Fresh);
+        assert_eq!(scrapes.load(Ordering::SeqCst), 1);
+
+        cache.get(key()).await.unwrap();
+        assert_eq!(scrapes.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn late_requests_join_the_scrape_with_its_earlier_steps() {
+        let (cache, scrapes) = counting_cache();
+        let Watched::Scraping(first) = cache.watch(key()) else {
+            panic!("nothing is cached yet");
+        };
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let Watched::Scraping(late) = cache.watch(key()) else {
+            panic!("the first scrape is still in progress");
+        };
+        let (mut early_steps, mut late_steps) = (Vec::new(), Vec::new());
+        let (a, b) = tokio::join!(
+            first.follow(|step| early_steps.push(step)),
+            late.follow(|step| late_steps.push(step)),
+        );
+        a.unwrap();
+        b.unwrap();
+        assert!(matches!(early_steps[..], [Step::PageLoaded { total: 0 }]));
+        assert!(matches!(late_steps[..], [Step::PageLoaded { total: 0 }]));
+        assert_eq!(scrapes.load(Ordering::SeqCst), 1);
+        assert!(matches!(cache.watch(key()), Watched::Cached(_)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stale_results_are_served_while_revalidating() {
+        let (cache, scrapes) = counting_cache();
+        let first = cache.get(key()).await.unwrap();
+
+        tokio::time::advance(TTL).await;
+        let stale = cache.get(key()).await.unwrap();
+        assert_eq!(stale.freshness, Freshness::Stale);
+        assert_eq!(stale.scraped_at, first.scraped_at);
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert_eq!(scrapes.load(Ordering::SeqCst), 2);
+        let refreshed = cache.get(key()).await.unwrap();
+        assert_eq!(refreshed.freshness, Freshness::Fresh);
+        assert_eq!(scrapes.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn peeking_never_scrapes() {
+        let (cache, scrapes) = counting_cache();
+        assert!(cache.peek(key()).is_none());
+        cache.get(key()).await.unwrap();
+        tokio::time::advance(TTL).await;
+        assert_eq!(cache.peek(key()).unwrap().freshness, Freshness::Stale);
+        assert_eq!(scrapes.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn expired_results_are_scraped_again() {
+        let (cache, scrapes) = counting_cache();
+        cache.get(key()).await.unwrap();
+
+        tokio::time::advance(TTL + STALE_TTL).await;
+        cache.remove_expired();
+        assert_eq!(cache.get(key()).await.unwrap().freshness, Freshness::Fresh);
+        assert_eq!(scrapes.load(Ordering::SeqCst), 2);
+    }
+}
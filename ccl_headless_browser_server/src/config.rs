@@ -0,0 +1,374 @@
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+use calgary_central_library::{
+    Config as SharedConfig, Profile as SharedProfile, WebDriverEndpoint,
+};
+use clap::Parser;
+use serde::Deserialize;
+use thiserror::Error;
+use tracing_subscriber::EnvFilter;
+
+use crate::feeds::FeedProfile;
+
+/// The crawling server for the rooms of the Calgary Central Library.
+///
+/// Every setting is taken from the first of: the command-line flag,
+/// the `CCLSHB_*` environment variable, the TOML configuration file, the default.
+/// The default WebDriver server is the one of the configuration that the front ends share.
+#[derive(Parser, Debug, Default)]
+#[command(version)]
+pub(crate) struct Cli {
+    /// Path to the TOML configuration file.
+    #[arg(long, env = "CCLSHB_CONFIG")]
+    config: Option<PathBuf>,
+    /// The address to listen on, e.g. `0.0.0.0:3000`.
+    #[arg(long, env = "CCLSHB_LISTEN_ADDR")]
+    listen_addr: Option<SocketAddr>,
+    /// The `host:port` addresses of the WebDriver servers. Sessions are spread across them.
+    #[arg(long = "web-driver", env = "CCLSHB_WEB_DRIVERS", value_delimiter = ',')]
+    web_drivers: Vec<WebDriverEndpoint>,
+    /// The maximum number of WebDriver sessions that are open at the same time.
+    #[arg(long, env = "CCLSHB_POOL_SIZE")]
+    pool_size: Option<usize>,
+    /// The number of requests a WebDriver session serves before it is replaced.
+    #[arg(long, env = "CCLSHB_MAX_SESSION_USES")]
+    max_session_uses: Option<u32>,
+    /// How long a request waits for a free WebDriver session, e.g. `2m`.
+    #[arg(long, env = "CCLSHB_ACQUIRE_TIMEOUT", value_parser = humantime::parse_duration)]
+    acquire_timeout: Option<Duration>,
+    /// How long scraping the booking page may take, e.g. `90s`.
+    #[arg(long, env = "CCLSHB_SCRAPE_TIMEOUT", value_parser = humantime::parse_duration)]
+    scrape_timeout: Option<Duration>,
+    /// How long checking the health of an idle WebDriver session may take.
+    #[arg(long, env = "CCLSHB_HEALTH_CHECK_TIMEOUT", value_parser = humantime::parse_duration)]
+    health_check_timeout: Option<Duration>,
+    /// How long finished scrape jobs can be polled for.
+    #[arg(long, env = "CCLSHB_JOB_TTL", value_parser = humantime::parse_duration)]
+    job_ttl: Option<Duration>,
+    /// How long scraped rooms are served from the cache as they are.
+    #[arg(long, env = "CCLSHB_CACHE_TTL", value_parser = humantime::parse_duration)]
+    cache_ttl: Option<Duration>,
+    /// How long after `cache_ttl` the cached rooms are still served while being scraped again.
+    #[arg(long, env = "CCLSHB_CACHE_STALE_TTL", value_parser = humantime::parse_duration)]
+    cache_stale_ttl: Option<Duration>,
+    /// A `tracing` filter directive, e.g. `info` or `ccl_headless_browser_server=debug`.
+    #[arg(long, env = "CCLSHB_LOG_LEVEL")]
+    log_level: Option<String>,
+    /// Path to the SQLite database where every scrape is recorded. Nothing is recorded if unset.
+    #[arg(long, env = "CCLSHB_SNAPSHOT_DB")]
+    snapshot_db: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Config {
+    pub(crate) listen_addr: SocketAddr,
+    pub(crate) web_drivers: Vec<WebDriverEndpoint>,
+    pub(crate) pool_size: usize,
+    pub(crate) max_session_uses: u32,
+    #[serde(with = "humantime_serde")]
+    pub(crate) acquire_timeout: Duration,
+    #[serde(with = "humantime_serde")]
+    pub(crate) scrape_timeout: Duration,
+    #[serde(with = "humantime_serde")]
+    pub(crate) health_check_timeout: Duration,
+    #[serde(with = "humantime_serde")]
+    pub(crate) job_ttl: Duration,
+    #[serde(with = "humantime_serde")]
+    pub(crate) cache_ttl: Duration,
+    #[serde(with = "humantime_serde")]
+    pub(crate) cache_stale_ttl: Duration,
+    pub(crate) log_level: String,
+    pub(crate) snapshot_db: Option<PathBuf>,
+    /// The calendar feeds, which can only be configured in the file.
+    pub(crate) feeds: Vec<FeedProfile>,
+    /// The group size of the requests without one, the one of the default profile
+    /// of the shared configuration.
+    #[serde(skip)]
+    pub(crate) default_group_size: u8,
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum ConfigError {
+    #[error("Failed to read the configuration file {path:?}: {source}")]
+    ReadFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse the configuration file {path:?}: {source}")]
+    ParseFile {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("At least one WebDriver server must be configured")]
+    NoWebDrivers,
+    #[error("`{0}` must be greater than zero")]
+    Zero(&'static str),
+    #[error("The feed {0:?} must have a unique name made of letters, digits, `-` and `_`")]
+    FeedName(String),
+    #[error("The feed {0:?} must have a group size and at least one weekday")]
+    EmptyFeed(String),
+    #[error(transparent)]
+    Shared(#[from] calgary_central_library::ConfigError),
+    #[error("`log_level` is not a valid filter directive: {0}")]
+    LogLevel(#[from] tracing_subscriber::filter::ParseError),
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listen_addr: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            web_drivers: vec![WebDriverEndpoint::default()],
+            pool_size: 4,
+            max_session_uses: 50,
+            acquire_timeout: Duration::from_secs(120),
+            scrape_timeout: Duration::from_secs(120),
+            health_check_timeout: Duration::from_secs(5),
+            job_ttl: Duration::from_secs(15 * 60),
+            cache_ttl: Duration::from_secs(10 * 60),
+            cache_stale_ttl: Duration::from_secs(60 * 60),
+            log_level: "info".to_owned(),
+            snapshot_db: None,
+            feeds: Vec::new(),
+            default_group_size: SharedProfile::default().group_size,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration from the command line, the environment and the configuration file.
+    pub(crate) fn load() -> Result<Self, ConfigError> {
+        let cli = Cli::parse();
+        let file = match &cli.config {
+            Some(path) => {
+                Some(
+                    std::fs::read_to_string(path).map_err(|source| ConfigError::ReadFile {
+                        path: path.clone(),
+                        source,
+                    })?,
+                )
+            }
+            None => None,
+        };
+        Self::from_layers(&SharedConfig::load()?, file.as_deref(), cli)
+    }
+
+    fn from_layers(
+        shared: &SharedConfig,
+        file: Option<&str>,
+        cli: Cli,
+    ) -> Result<Self, ConfigError> {
+        let mut config = match file {
+            Some(file) => toml::from_str(file).map_err(|source| ConfigError::ParseFile {
+                path: cli.config.clone().unwrap_or_default(),
+                source,
+            })?,
+            None => Config::default(),
+        };
+        let file_has_web_drivers = file.is_some_and(|file| {
+            toml::from_str::<toml::Table>(file).is_ok_and(|table| table.contains_key("web_drivers"))
+        });
+        config.default_group_size = shared.profile(None)?.group_size;
+        if !file_has_web_drivers {
+            config.web_drivers = vec![shared.web_driver.clone()];
+        }
+
+        let Cli {
+            config: _,
+            listen_addr,
+            web_drivers,
+            pool_size,
+            max_session_uses,
+            acquire_timeout,
+            scrape_timeout,
+            health_check_timeout,
+            job_ttl,
+            cache_ttl,
+            cache_stale_ttl,
+            log_level,
+            snapshot_db,
+        } = cli;
+        if let Some(listen_addr) = listen_addr {
+            config.listen_addr = listen_addr;
+        }
+        if !web_drivers.is_empty() {
+            config.web_drivers = web_drivers;
+        }
+        if let Some(pool_size) = pool_size {
+            config.pool_size = pool_size;
+        }
+        if let Some(max_session_uses) = max_session_uses {
+            config.max_session_uses = max_session_uses;
+        }
+        if let Some(acquire_timeout) = acquire_timeout {
+            config.acquire_timeout = acquire_timeout;
+        }
+        if let Some(scrape_timeout) = scrape_timeout {
+            config.scrape_timeout = scrape_timeout;
+        }
+        if let Some(health_check_timeout) = health_check_timeout {
+            config.health_check_timeout = health_check_timeout;
+        }
+        if let Some(job_ttl) = job_ttl {
+            config.job_ttl = job_ttl;
+        }
+        if let Some(cache_ttl) = cache_ttl {
+            config.cache_ttl = cache_ttl;
+        }
+        if let Some(cache_stale_ttl) = cache_stale_ttl {
+            config.cache_stale_ttl = cache_stale_ttl;
+        }
+        if let Some(log_level) = log_level {
+            config.log_level = log_level;
+        }
+        if let Some(snapshot_db) = snapshot_db {
+            config.snapshot_db = Some(snapshot_db);
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.web_drivers.is_empty() {
+            return Err(ConfigError::NoWebDrivers);
+        }
+        if self.pool_size == 0 {
+            return Err(ConfigError::Zero("pool_size"));
+        }
+        if self.max_session_uses == 0 {
+            return Err(ConfigError::Zero("max_session_uses"));
+        }
+        if self.acquire_timeout.is_zero() {
+            return Err(ConfigError::Zero("acquire_timeout"));
+        }
+        if self.scrape_timeout.is_zero() {
+            return Err(ConfigError::Zero("scrape_timeout"));
+        }
+        if self.health_check_timeout.is_zero() {
+            return Err(ConfigError::Zero("health_check_timeout"));
+        }
+        for (i, feed) in self.feeds.iter().enumerate() {
+            let valid_name = !feed.name.is_empty()
+                && feed
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                && self.feeds[..i].iter().all(|other| other.name != feed.name);
+            if !valid_name {
+                return Err(ConfigError::FeedName(feed.name.clone()));
+            }
+            if feed.group_size == 0 || feed.weekdays.is_empty() || feed.weeks_ahead == 0 {
+                return Err(ConfigError::EmptyFeed(feed.name.clone()));
+            }
+        }
+        self.log_filter()?;
+        Ok(())
+    }
+
+    pub(crate) fn log_filter(&self) -> Result<EnvFilter, tracing_subscriber::filter::ParseError> {
+        EnvFilter::try_new(&self.log_level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid() {
+        let config = Config::from_layers(&SharedConfig::default(), None, Cli::default()).unwrap();
+        assert_eq!(config.listen_addr, "0.0.0.0:3000".parse().unwrap());
+        assert_eq!(config.web_drivers[0].to_string(), "localhost:4444");
+    }
+
+    #[test]
+    fn example_config_is_valid() {
+        let file = include_str!("../config.example.toml");
+        Config::from_layers(&SharedConfig::default(), Some(file), Cli::default()).unwrap();
+    }
+
+    #[test]
+    fn command_line_overrides_file() {
+        let file = r#"
+            web_drivers = ["chromium-chromedriver:4444", "localhost:9515"]
+            pool_size = 8
+            scrape_timeout = "90s"
+        "#;
+        let cli = Cli::parse_from(["ccl_headless_browser_server", "--pool-size", "2"]);
+        let config = Config::from_layers(&SharedConfig::default(), Some(file), cli).unwrap();
+        assert_eq!(config.web_drivers.len(), 2);
+        assert_eq!(config.pool_size, 2);
+        assert_eq!(config.scrape_timeout, Duration::from_secs(90));
+    }
+
+    #[test]
+    fn shared_web_driver_is_the_default() {
+        let shared = SharedConfig {
+            web_driver: "chromium-chromedriver:9515".parse().unwrap(),
+            ..SharedConfig::default()
+        };
+        let config = Config::from_layers(&shared, Some("pool_size = 2"), Cli::default()).unwrap();
+        assert_eq!(
+            config.web_drivers[0].to_string(),
+            "chromium-chromedriver:9515"
+        );
+        let file = r#"web_drivers = ["localhost:4444"]"#;
+        let config = Config::from_layers(&shared, Some(file), Cli::default()).unwrap();
+        assert_eq!(config.web_drivers[0].to_string(), "localhost:4444");
+    }
+
+    #[test]
+    fn default_group_size_is_the_one_of_the_default_profile() {
+        let shared: SharedConfig = toml::from_str(
+            r#"
+                default_profile = "meetups"
+                [profiles.meetups]
+                group_size = 20
+            "#,
+        )
+        .unwrap();
+        let config = Config::from_layers(&shared, None, Cli::default()).unwrap();
+        assert_eq!(config.default_group_size, 20);
+    }
+
+    #[test]
+    fn zero_pool_size_is_rejected() {
+        let err = Config::from_layers(
+            &SharedConfig::default(),
+            Some("pool_size = 0"),
+            Cli::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConfigError::Zero("pool_size")));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let err = Config::from_layers(
+            &SharedConfig::default(),
+            Some("pool_sise = 2"),
+            Cli::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConfigError::ParseFile { .. }));
+    }
+
+    #[test]
+    fn feed_names_are_unique() {
+        let file = r#"
+            [[feeds]]
+            name = "thursdays"
+            group_size = 20
+            weekdays = ["Thu"]
+
+            [[feeds]]
+            name = "thursdays"
+            group_size = 10
+            weekdays = ["Thu"]
+        "#;
+        let err =
+            Config::from_layers(&SharedConfig::default(), Some(file), Cli::default()).unwrap_err();
+        assert!(matches!(err, ConfigError::FeedName(name) if name == "thursdays"));
+    }
+}
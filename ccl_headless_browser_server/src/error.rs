@@ -0,0 +1,209 @@
+use axum::{
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        FromRequest, FromRequestParts,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{pool::AcquireError, scrape::ScrapeError};
+
+/// Same as [`axum::extract::Query`] but rejects with an [`ApiError`].
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(ApiError))]
+pub(crate) struct ApiQuery<T>(pub(crate) T);
+
+/// Same as [`axum::extract::Path`] but rejects with an [`ApiError`].
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(ApiError))]
+pub(crate) struct ApiPath<T>(pub(crate) T);
+
+/// Same as [`axum::Json`] but rejects with an [`ApiError`].
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(ApiError))]
+pub(crate) struct ApiJson<T>(pub(crate) T);
+
+/// A stable, machine-readable identifier of a failure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ErrorCode {
+    /// The request is malformed, e.g. a parameter is missing or cannot be parsed.
+    InvalidRequest,
+    /// The date is in the past.
+    InvalidDate,
+    /// The group size is zero.
+    InvalidGroupSize,
+    UnknownBranch,
+    /// The job does not exist or has expired.
+    UnknownJob,
+    /// No scrape has been recorded for the query or recording is disabled.
+    NoSnapshot,
+    /// No calendar feed with this name is configured.
+    UnknownFeed,
+    /// Every WebDriver session stayed busy for too long.
+    ServerBusy,
+    /// No WebDriver session could be created or the connection to it was lost.
+    WebDriverUnreachable,
+    /// The library website could not be loaded.
+    LibrarySiteUnreachable,
+    /// The library website no longer has the structure that the scraper expects.
+    LibrarySiteChanged,
+    /// The scrape took too long.
+    Timeout,
+    /// The WebDriver server failed in an unexpected way.
+    ScrapeFailed,
+    /// The recorded scrapes could not be read.
+    SnapshotStoreFailed,
+}
+
+/// The JSON body of every error response.
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub(crate) struct ErrorBody {
+    pub(crate) code: ErrorCode,
+    /// A human-readable description of the failure.
+    pub(crate) message: String,
+    /// Whether repeating the same request later may succeed.
+    pub(crate) retryable: bool,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ApiError {
+    pub(crate) status: StatusCode,
+    pub(crate) body: ErrorBody,
+}
+
+impl ErrorCode {
+    fn status(self) -> StatusCode {
+        use ErrorCode::*;
+        match self {
+            InvalidRequest | InvalidDate | InvalidGroupSize => StatusCode::BAD_REQUEST,
+            UnknownBranch | UnknownJob | NoSnapshot | UnknownFeed => StatusCode::NOT_FOUND,
+            ServerBusy | WebDriverUnreachable => StatusCode::SERVICE_UNAVAILABLE,
+            LibrarySiteUnreachable | LibrarySiteChanged | ScrapeFailed => StatusCode::BAD_GATEWAY,
+            Timeout => StatusCode::GATEWAY_TIMEOUT,
+            SnapshotStoreFailed => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn is_retryable(self) -> bool {
+        use ErrorCode::*;
+        match self {
+            ServerBusy | WebDriverUnreachable | LibrarySiteUnreachable | Timeout | ScrapeFailed => {
+                true
+            }
+            InvalidRequest | InvalidDate | InvalidGroupSize | UnknownBranch | UnknownJob
+            | NoSnapshot | UnknownFeed | LibrarySiteChanged | SnapshotStoreFailed => false,
+        }
+    }
+}
+
+impl ApiError {
+    pub(crate) fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            status: code.status(),
+            body: ErrorBody {
+                code,
+                message: message.into(),
+                retryable: code.is_retryable(),
+            },
+        }
+    }
+
+    pub(crate) fn unknown_branch(branch_id: u32) -> Self {
+        Self::new(
+            ErrorCode::UnknownBranch,
+            format!("There is no branch with the identifier {branch_id}"),
+        )
+    }
+
+    pub(crate) fn no_snapshot() -> Self {
+        Self::new(
+            ErrorCode::NoSnapshot,
+            "No scrape has been recorded for this date and group size",
+        )
+    }
+
+    pub(crate) fn unknown_feed(file: &str) -> Self {
+        Self::new(
+            ErrorCode::UnknownFeed,
+            format!("There is no calendar feed at /feeds/{file}"),
+        )
+    }
+
+    pub(crate) fn unknown_job() -> Self {
+        Self::new(
+            ErrorCode::UnknownJob,
+            "The job does not exist or has expired",
+        )
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.body)).into_response()
+    }
+}
+
+impl From<ScrapeError> for ApiError {
+    fn from(e: ScrapeError) -> Self {
+        let code = match &e {
+            ScrapeError::Acquire(AcquireError::Timeout) => ErrorCode::ServerBusy,
+            ScrapeError::Acquire(AcquireError::New(_)) => ErrorCode::WebDriverUnreachable,
+            ScrapeError::Timeout(_) => ErrorCode::Timeout,
+            ScrapeError::AvailableRooms(e) if e.is_page_structure_changed() => {
+                ErrorCode::LibrarySiteChanged
+            }
+            ScrapeError::AvailableRooms(e) if e.is_web_driver_connection_lost() => {
+                ErrorCode::WebDriverUnreachable
+            }
+            ScrapeError::AvailableRooms(e) if e.is_navigation_failed() => {
+                ErrorCode::LibrarySiteUnreachable
+            }
+            ScrapeError::AvailableRooms(_) => ErrorCode::ScrapeFailed,
+        };
+        Self::new(code, e.to_string())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::new(ErrorCode::InvalidRequest, rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::new(ErrorCode::InvalidRequest, rejection.body_text())
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(ErrorCode::InvalidRequest, rejection.body_text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn busy_pool_is_worth_retrying() {
+        let e = ApiError::from(ScrapeError::Acquire(AcquireError::Timeout));
+        assert_eq!(e.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(e.body.code, ErrorCode::ServerBusy);
+        assert!(e.body.retryable);
+    }
+
+    #[test]
+    fn error_codes_are_snake_case() {
+        let body = ApiError::new(ErrorCode::LibrarySiteChanged, "").body;
+        let json = serde_json::to_value(body).unwrap();
+        assert_eq!(json["code"], "library_site_changed");
+        assert_eq!(json["retryable"], false);
+    }
+}
@@ -0,0 +1,238 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{extract::State, http::header, response::IntoResponse, Json};
+use calgary_central_library::{
+    library_today, Calendar, Snapshot, SnapshotStore, TimeWindow, WatchRule,
+};
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    api::CENTRAL_LIBRARY_BRANCH_ID,
+    cache::RoomsCache,
+    error::{ApiError, ApiPath, ErrorBody},
+    scrape::Scraper,
+    CCLSHBServerState,
+};
+
+/// The rooms that an organizer wants to see in their calendar client, e.g.
+/// "rooms for 20 people from 6 to 9 PM on Thursdays".
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FeedProfile {
+    /// The name in the URL of the feed, e.g. `thursday-evenings` for `/feeds/thursday-evenings.ics`.
+    pub(crate) name: String,
+    pub(crate) group_size: u8,
+    /// Only the free time in this window is listed, and only for the rooms that are free during all of it.
+    #[serde(default)]
+    pub(crate) time_window: Option<TimeWindow>,
+    #[schema(value_type = Vec<String>, example = json!(["Thu"]))]
+    pub(crate) weekdays: Vec<Weekday>,
+    /// How many weeks ahead, counting from today, the feed covers.
+    #[serde(default = "default_weeks_ahead")]
+    pub(crate) weeks_ahead: u32,
+    /// The prefixes of the titles of the acceptable rooms, e.g. `3-20A`. Any room is listed if empty.
+    #[serde(default)]
+    pub(crate) rooms: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct FeedView {
+    #[serde(flatten)]
+    profile: FeedProfile,
+    /// The path of the feed to subscribe to.
+    url: String,
+}
+
+fn default_weeks_ahead() -> u32 {
+    4
+}
+
+impl FeedProfile {
+    /// The dates that the feed covers, starting from `today`.
+    pub(crate) fn dates(&self, today: NaiveDate) -> Vec<NaiveDate> {
+        today
+            .iter_days()
+            .take(7 * self.weeks_ahead as usize)
+            .filter(|date| self.weekdays.contains(&date.weekday()))
+            .collect()
+    }
+
+    fn rule(&self) -> WatchRule {
+        WatchRule {
+            name: self.name.clone(),
+            group_size: self.group_size,
+            time_window: self.time_window,
+            dates: Vec::new(),
+            rooms: self.rooms.clone(),
+        }
+    }
+
+    /// Keeps only the rooms of `snapshot` that the profile is interested in,
+    /// with their free time limited to the time window.
+    fn filter(&self, mut snapshot: Snapshot) -> Snapshot {
+        let rule = self.rule();
+        snapshot.rooms = rule
+            .matching_rooms(&snapshot.rooms)
+            .into_iter()
+            .map(|(room, availability)| match self.time_window {
+                Some(TimeWindow { from, to }) => (room, availability.within(from, to)),
+                None => (room, availability),
+            })
+            .collect();
+        snapshot
+    }
+}
+
+/// Scrapes the dates of every feed again whenever their cached rooms get old,
+/// so that the feeds can be served from the cache alone.
+pub(crate) async fn keep_warm(feeds: Arc<Vec<FeedProfile>>, cache: Arc<RoomsCache>, ttl: Duration) {
+    let mut interval = tokio::time::interval(ttl.max(Duration::from_secs(60)));
+    loop {
+        interval.tick().await;
+        let today = library_today();
+        let mut keys = Vec::new();
+        for feed in feeds.iter() {
+            for date in feed.dates(today) {
+                let key = (CENTRAL_LIBRARY_BRANCH_ID, date, feed.group_size);
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        for key in keys {
+            if let Err(e) = cache.get(key).await {
+                tracing::warn!(
+                    "Failed to refresh the rooms of a feed for {}: {}",
+                    key.1,
+                    e.body.message
+                );
+            }
+        }
+    }
+}
+
+/// The most recent rooms for the date without scraping them: from the cache or else from the recorded snapshots.
+async fn latest_snapshot(
+    cache: &RoomsCache,
+    scraper: &Scraper,
+    date: NaiveDate,
+    group_size: u8,
+) -> Option<Snapshot> {
+    if let Some(response) = cache.peek((CENTRAL_LIBRARY_BRANCH_ID, date, group_size)) {
+        return Some(Snapshot {
+            date,
+            group_size,
+            scraped_at: response.scraped_at,
+            rooms: response.rooms.to_vec(),
+        });
+    }
+    let snapshots = scraper.snapshots.clone()?;
+    tokio::task::spawn_blocking(move || snapshots.latest(date, Some(group_size)))
+        .await
+        .ok()?
+        .inspect_err(|e| tracing::warn!("Failed to read the snapshot for {date}: {e}"))
+        .ok()?
+}
+
+/// Lists the calendar feeds that can be subscribed to.
+#[utoipa::path(get, path = "/feeds", responses((status = 200, body = Vec<FeedView>)))]
+pub(crate) async fn feeds(State(state): State<CCLSHBServerState>) -> Json<Vec<FeedView>> {
+    Json(
+        state
+            .feeds
+            .iter()
+            .map(|profile| FeedView {
+                url: format!("/feeds/{}.ics", profile.name),
+                profile: profile.clone(),
+            })
+            .collect(),
+    )
+}
+
+/// Returns a calendar feed with the free time of the rooms of its profile as tentative events.
+///
+/// The feed is built from the rooms that were scraped last and never waits for a scrape.
+/// Dates that haven't been scraped yet are left out.
+#[utoipa::path(
+    get,
+    path = "/feeds/{name}.ics",
+    params(("name" = String, Path, description = "The name of the feed profile")),
+    responses(
+        (status = 200, content_type = "text/calendar", body = String),
+        (status = 404, body = ErrorBody, description = "Unknown feed"),
+    )
+)]
+pub(crate) async fn feed(
+    State(state): State<CCLSHBServerState>,
+    ApiPath(file): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let profile = file
+        .strip_suffix(".ics")
+        .and_then(|name| state.feeds.iter().find(|feed| feed.name == name))
+        .ok_or_else(|| ApiError::unknown_feed(&file))?;
+    let mut calendar = Calendar::new(format!("Free rooms: {}", profile.name));
+    let today = library_today();
+    for date in profile.dates(today) {
+        let snapshot =
+            latest_snapshot(&state.cache, &state.scraper, date, profile.group_size).await;
+        if let Some(snapshot) = snapshot {
+            calendar.add_free_windows(&profile.filter(snapshot));
+        }
+    }
+    Ok((
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        calendar.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use calgary_central_library::fixtures;
+    use chrono::{NaiveTime, Utc};
+
+    use super::*;
+
+    fn profile() -> FeedProfile {
+        toml::from_str(
+            r#"
+                name = "thursday-evenings"
+                group_size = 20
+                time_window = { from = "18:00", to = "20:00" }
+                weekdays = ["Thu"]
+                weeks_ahead = 2
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn dates_fall_on_the_weekdays() {
+        let thursday = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        let dates = profile().dates(thursday);
+        assert_eq!(
+            dates,
+            [thursday, NaiveDate::from_ymd_opt(2024, 3, 21).unwrap()]
+        );
+    }
+
+    #[test]
+    fn free_time_is_limited_to_the_time_window() {
+        let rooms = vec![fixtures::free_room(
+            "3-20A Idea Lab",
+            &[
+                "6:00 AM", "6:00 PM", "6:30 PM", "7:00 PM", "7:30 PM", "8:00 PM",
+            ],
+        )];
+        let snapshot = Snapshot {
+            date: NaiveDate::from_ymd_opt(2024, 3, 14).unwrap(),
+            group_size: 20,
+            scraped_at: Utc::now(),
+            rooms,
+        };
+        let filtered = profile().filter(snapshot);
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        assert_eq!(filtered.rooms[0].1.free_ranges(), [(time(18), time(20))]);
+    }
+}
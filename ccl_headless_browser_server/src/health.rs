@@ -0,0 +1,178 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{extract::State, http::StatusCode, Json};
+use calgary_central_library::{booking_url, library_today, AsyncClient, Client};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::{
+    error::{ApiError, ErrorBody, ErrorCode},
+    pool::AcquireError,
+    scrape::Scraper,
+    CCLSHBServerState,
+};
+
+/// How many days ahead the canary date is. The booking page only lists upcoming days.
+const CANARY_DAYS_AHEAD: i64 = 7;
+/// How long a readiness result is reused so that frequent probes don't occupy the pool.
+const READINESS_CACHE_TTL: Duration = Duration::from_secs(30);
+/// How long a probe waits for a free session. It must answer well before the orchestrator
+/// gives up on it, even while every session is busy with requests.
+const PROBE_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Serialize, ToSchema)]
+pub(crate) struct Readiness {
+    ready: bool,
+    /// Every session was busy with requests, so the booking page wasn't checked again.
+    /// The result is the one of the last check, or ready if there was none, since the busy
+    /// sessions show that the server works.
+    busy: bool,
+    checked_at: DateTime<Utc>,
+    /// Why the server is not ready.
+    error: Option<ErrorBody>,
+}
+
+#[derive(Error, Debug)]
+enum ProbeError {
+    #[error(transparent)]
+    Acquire(#[from] AcquireError),
+    #[error("Failed to load the booking page: {0}")]
+    Navigate(<Client as AsyncClient>::NavigateToUrlError),
+    #[error("Failed to find the search button on the booking page: {0}")]
+    FindSearchButton(<Client as AsyncClient>::FindSearchButtonError),
+    #[error("Checking the booking page took longer than {0:?}")]
+    Timeout(Duration),
+}
+
+/// Checks that a WebDriver session can be opened and that the booking page
+/// still has the structure that the scraper expects.
+pub(crate) struct ReadinessProbe {
+    scraper: Arc<Scraper>,
+    last: tokio::sync::Mutex<Option<Readiness>>,
+}
+
+impl From<ProbeError> for ApiError {
+    fn from(e: ProbeError) -> Self {
+        let code = match &e {
+            ProbeError::Acquire(AcquireError::Timeout) => ErrorCode::ServerBusy,
+            ProbeError::Acquire(AcquireError::New(_)) => ErrorCode::WebDriverUnreachable,
+            ProbeError::Navigate(_) => ErrorCode::LibrarySiteUnreachable,
+            ProbeError::FindSearchButton(e) if e.is_page_structure_changed() => {
+                ErrorCode::LibrarySiteChanged
+            }
+            ProbeError::FindSearchButton(_) => ErrorCode::ScrapeFailed,
+            ProbeError::Timeout(_) => ErrorCode::Timeout,
+        };
+        ApiError::new(code, e.to_string())
+    }
+}
+
+impl ReadinessProbe {
+    pub(crate) fn new(scraper: Arc<Scraper>) -> Self {
+        Self {
+            scraper,
+            last: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    pub(crate) async fn check(&self) -> Readiness {
+        // Concurrent probes wait for the check that is already in progress
+        let mut last = self.last.lock().await;
+        if let Some(readiness) = last.as_ref() {
+            let age = (Utc::now() - readiness.checked_at)
+                .to_std()
+                .unwrap_or_default();
+            if age < READINESS_CACHE_TTL {
+                return readiness.clone();
+            }
+        }
+        let readiness = match self.find_search_button().await {
+            Ok(()) => Readiness {
+                ready: true,
+                busy: false,
+                checked_at: Utc::now(),
+                error: None,
+            },
+            Err(ProbeError::Acquire(AcquireError::Timeout)) => {
+                let readiness = last.clone().unwrap_or(Readiness {
+                    ready: true,
+                    busy: false,
+                    checked_at: Utc::now(),
+                    error: None,
+                });
+                // Not cached, so that the next probe checks again
+                return Readiness {
+                    busy: true,
+                    ..readiness
+                };
+            }
+            Err(e) => {
+                tracing::warn!("The readiness check failed: {e}");
+                Readiness {
+                    ready: false,
+                    busy: false,
+                    checked_at: Utc::now(),
+                    error: Some(ApiError::from(e).body),
+                }
+            }
+        };
+        *last = Some(readiness.clone());
+        readiness
+    }
+
+    async fn find_search_button(&self) -> Result<(), ProbeError> {
+        let canary_date = library_today() + chrono::Duration::try_days(CANARY_DAYS_AHEAD).unwrap();
+        let mut client = self
+            .scraper
+            .pool
+            .acquire_within(PROBE_ACQUIRE_TIMEOUT)
+            .await?;
+        let result = tokio::time::timeout(self.scraper.timeout, async {
+            client
+                .navigate_to_url(&booking_url(canary_date, 1))
+                .await
+                .map_err(ProbeError::Navigate)?;
+            client
+                .find_search_button()
+                .await
+                .map_err(ProbeError::FindSearchButton)?;
+            Ok(())
+        })
+        .await
+        .unwrap_or(Err(ProbeError::Timeout(self.scraper.timeout)));
+        if result.is_err() {
+            client.mark_broken();
+        }
+        result
+    }
+}
+
+/// Reports that the process is up. It doesn't check any dependency.
+#[utoipa::path(get, path = "/healthz", responses((status = 200)))]
+pub(crate) async fn healthz() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Reports whether rooms can be scraped right now by opening the booking page
+/// for a canary date and looking for its search button.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, body = Readiness),
+        (status = 503, body = Readiness),
+    )
+)]
+pub(crate) async fn readyz(
+    State(state): State<CCLSHBServerState>,
+) -> (StatusCode, Json<Readiness>) {
+    let readiness = state.readiness.check().await;
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
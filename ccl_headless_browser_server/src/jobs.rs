@@ -0,0 +1,273 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use calgary_central_library::{Availability, Room};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use tokio::task::AbortHandle;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    cache::{CacheKey, RoomsCache, Step, Watched},
+    error::{ApiError, ErrorBody},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum JobStatus {
+    /// The job waits for a WebDriver session or for the booking page to load.
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Copy, Default, Serialize, ToSchema)]
+pub(crate) struct Progress {
+    rooms_done: usize,
+    /// Unknown until the booking page has been loaded.
+    rooms_total: Option<usize>,
+}
+
+/// The publicly visible state of a scrape job.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct JobView {
+    pub(crate) id: Uuid,
+    date: NaiveDate,
+    group_size: u8,
+    pub(crate) status: JobStatus,
+    progress: Progress,
+    created_at: DateTime<Utc>,
+    /// When the finished job will be forgotten.
+    expires_at: Option<DateTime<Utc>>,
+    results: Option<Vec<(Room, Availability)>>,
+    error: Option<ErrorBody>,
+}
+
+struct Job {
+    date: NaiveDate,
+    group_size: u8,
+    status: JobStatus,
+    progress: Progress,
+    created_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+    results: Option<Vec<(Room, Availability)>>,
+    error: Option<ErrorBody>,
+    abort_handle: Option<AbortHandle>,
+}
+
+/// Scrape jobs that clients enqueue and then poll.
+pub(crate) struct JobRegistry {
+    jobs: Mutex<HashMap<Uuid, Job>>,
+    /// How long a finished job is kept around for polling.
+    ttl: chrono::Duration,
+}
+
+impl Job {
+    fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            JobStatus::Succeeded | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+
+    fn expires_at(&self, ttl: chrono::Duration) -> Option<DateTime<Utc>> {
+        self.finished_at.map(|finished_at| finished_at + ttl)
+    }
+
+    fn view(&self, id: Uuid, ttl: chrono::Duration) -> JobView {
+        JobView {
+            id,
+            date: self.date,
+            group_size: self.group_size,
+            status: self.status,
+            progress: self.progress,
+            created_at: self.created_at,
+            expires_at: self.expires_at(ttl),
+            results: self.results.clone(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+impl JobRegistry {
+    pub(crate) fn new(ttl: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            jobs: Mutex::new(HashMap::new()),
+            ttl: chrono::Duration::from_std(ttl).expect("the job TTL is out of range"),
+        })
+    }
+
+    /// Enqueues a scrape and returns the state of the new job. The job shares the scrape
+    /// with the identical requests in progress and succeeds right away if the rooms are cached.
+    pub(crate) fn start(self: &Arc<Self>, cache: &Arc<RoomsCache>, key: CacheKey) -> JobView {
+        let (_branch_id, date, group_size) = key;
+        let id = self.insert(date, group_size);
+        let registry = self.clone();
+        let watched = cache.watch(key);
+        // Cancelling the job only stops following the scrape, which still fills the cache
+        let handle = tokio::spawn(async move {
+            let result = match watched {
+                Watched::Cached(response) => Ok(response),
+                Watched::Scraping(scrape) => {
+                    scrape.follow(|step| registry.on_progress(id, &step)).await
+                }
+            };
+            registry.finish(id, result.map(|response| response.rooms.to_vec()));
+        });
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(&id).unwrap();
+        if !job.is_finished() {
+            job.abort_handle = Some(handle.abort_handle());
+        }
+        job.view(id, self.ttl)
+    }
+
+    fn insert(&self, date: NaiveDate, group_size: u8) -> Uuid {
+        let id = Uuid::new_v4();
+        let job = Job {
+            date,
+            group_size,
+            status: JobStatus::Queued,
+            progress: Progress::default(),
+            created_at: Utc::now(),
+            finished_at: None,
+            results: None,
+            error: None,
+            abort_handle: None,
+        };
+        self.jobs.lock().unwrap().insert(id, job);
+        id
+    }
+
+    fn on_progress(&self, id: Uuid, step: &Step) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(job) = jobs.get_mut(&id) else {
+            return;
+        };
+        job.status = JobStatus::Running;
+        job.progress = match *step {
+            Step::PageLoaded { total } => Progress {
+                rooms_done: 0,
+                rooms_total: Some(total),
+            },
+            Step::RoomParsed { index, total, .. } => Progress {
+                rooms_done: index + 1,
+                rooms_total: Some(total),
+            },
+        };
+    }
+
+    fn finish(&self, id: Uuid, result: Result<Vec<(Room, Availability)>, ApiError>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(job) = jobs.get_mut(&id) else {
+            return;
+        };
+        if job.is_finished() {
+            return;
+        }
+        match result {
+            Ok(rooms) => {
+                job.status = JobStatus::Succeeded;
+                job.progress = Progress {
+                    rooms_done: rooms.len(),
+                    rooms_total: Some(rooms.len()),
+                };
+                job.results = Some(rooms);
+            }
+            Err(e) => {
+                job.status = JobStatus::Failed;
+                job.error = Some(e.body);
+            }
+        }
+        job.finished_at = Some(Utc::now());
+        job.abort_handle = None;
+    }
+
+    pub(crate) fn get(&self, id: Uuid) -> Option<JobView> {
+        let jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(&id)?;
+        if job.expires_at(self.ttl).is_some_and(|t| t <= Utc::now()) {
+            return None;
+        }
+        Some(job.view(id, self.ttl))
+    }
+
+    /// Stops a queued or running job. Finished jobs are left as they are.
+    pub(crate) fn cancel(&self, id: Uuid) -> Option<JobView> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(&id)?;
+        if !job.is_finished() {
+            if let Some(abort_handle) = job.abort_handle.take() {
+                abort_handle.abort();
+            }
+            job.status = JobStatus::Cancelled;
+            job.finished_at = Some(Utc::now());
+        }
+        Some(job.view(id, self.ttl))
+    }
+
+    /// Forgets the jobs that finished more than the TTL ago.
+    pub(crate) fn remove_expired(&self) {
+        let now = Utc::now();
+        self.jobs
+            .lock()
+            .unwrap()
+            .retain(|_, job| job.expires_at(self.ttl).is_none_or(|t| t > now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pool::AcquireError, scrape::ScrapeError};
+
+    fn some_date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 14).unwrap()
+    }
+
+    #[test]
+    fn failed_job_reports_the_error() {
+        let registry = JobRegistry::new(Duration::from_secs(60));
+        let id = registry.insert(some_date(), 10);
+        registry.finish(
+            id,
+            Err(ApiError::from(ScrapeError::Acquire(AcquireError::Timeout))),
+        );
+
+        let view = registry.get(id).unwrap();
+        assert_eq!(view.status, JobStatus::Failed);
+        assert!(view.error.is_some());
+        assert!(view.expires_at.is_some());
+    }
+
+    #[test]
+    fn cancelled_job_ignores_the_late_result() {
+        let registry = JobRegistry::new(Duration::from_secs(60));
+        let id = registry.insert(some_date(), 10);
+        assert_eq!(registry.cancel(id).unwrap().status, JobStatus::Cancelled);
+        registry.finish(id, Ok(Vec::new()));
+
+        let view = registry.get(id).unwrap();
+        assert_eq!(view.status, JobStatus::Cancelled);
+        assert!(view.results.is_none());
+    }
+
+    #[test]
+    fn finished_jobs_expire() {
+        let registry = JobRegistry::new(Duration::ZERO);
+        let running = registry.insert(some_date(), 10);
+        let finished = registry.insert(some_date(), 10);
+        registry.finish(finished, Ok(Vec::new()));
+
+        assert!(registry.get(finished).is_none());
+        registry.remove_expired();
+        assert_eq!(registry.jobs.lock().unwrap().len(), 1);
+        assert!(registry.get(running).is_some());
+    }
+}
@@ -1,54 +1,160 @@
-use arcstr::ArcStr;
-use axum::{debug_handler, extract::State, http::StatusCode, routing::get, Json, Router};
-use calgary_central_library::{AsyncClient, Availability, Client, Room};
-use chrono::NaiveDate;
-use serde::{Deserialize, Serialize};
-
-#[derive(Deserialize, Serialize)]
-struct AvailableRoomsArgs {
-    date: NaiveDate,
-    group_size: u8,
-}
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    http::{header, Method},
+    routing::{get, post},
+    Router,
+};
+use calgary_central_library::SqliteSnapshotStore;
+use futures::FutureExt;
+use tower_http::cors::{Any, CorsLayer};
+
+use cache::{RoomsCache, Steps};
+use config::Config;
+use feeds::FeedProfile;
+use health::ReadinessProbe;
+use jobs::JobRegistry;
+use pool::{ClientPool, PoolConfig};
+use scrape::Scraper;
+
+mod api;
+mod cache;
+mod config;
+mod error;
+mod feeds;
+mod health;
+mod jobs;
+mod pool;
+mod scrape;
 
 #[derive(Clone)]
 struct CCLSHBServerState {
-    web_driver_host: ArcStr,
-    web_driver_port: u16,
+    scraper: Arc<Scraper>,
+    cache: Arc<RoomsCache>,
+    jobs: Arc<JobRegistry>,
+    readiness: Arc<ReadinessProbe>,
+    feeds: Arc<Vec<FeedProfile>>,
+    default_group_size: u8,
 }
 
 #[tokio::main]
 async fn main() {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {e}");
+            std::process::exit(2);
+        }
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(config.log_filter().unwrap())
+        .init();
+    tracing::debug!("{config:#?}");
+
+    let pool = ClientPool::new(PoolConfig {
+        web_drivers: config.web_drivers.clone(),
+        max_size: config.pool_size,
+        max_uses: config.max_session_uses,
+        acquire_timeout: config.acquire_timeout,
+        health_check_timeout: config.health_check_timeout,
+    });
+    let snapshots = match &config.snapshot_db {
+        Some(path) => match SqliteSnapshotStore::open(path) {
+            Ok(store) => Some(Arc::new(store)),
+            Err(e) => {
+                eprintln!("Failed to open the snapshot database {path:?}: {e}");
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let scraper = Arc::new(Scraper {
+        pool: pool.clone(),
+        timeout: config.scrape_timeout,
+        snapshots,
+    });
+
+    let readiness = Arc::new(ReadinessProbe::new(scraper.clone()));
+    let cache = RoomsCache::new(config.cache_ttl, config.cache_stale_ttl, {
+        let scraper = scraper.clone();
+        Arc::new(move |(_branch_id, date, group_size), steps: Arc<Steps>| {
+            let scraper = scraper.clone();
+            async move {
+                let rooms = scraper
+                    .available_rooms(date, group_size, |progress| steps.report(progress))
+                    .await?;
+                Ok(rooms)
+            }
+            .boxed()
+        })
+    });
+    let jobs = JobRegistry::new(config.job_ttl);
+    let feeds = Arc::new(config.feeds.clone());
+    if !feeds.is_empty() {
+        tokio::spawn(feeds::keep_warm(
+            feeds.clone(),
+            cache.clone(),
+            config.cache_ttl,
+        ));
+    }
+    tokio::spawn({
+        let cache = cache.clone();
+        let jobs = jobs.clone();
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                cache.remove_expired();
+                jobs.remove_expired();
+            }
+        }
+    });
+
+    // The React front and third-party scripts call the server from other origins
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_headers([header::CONTENT_TYPE]);
+
     let app = Router::new()
-        .route("/available_rooms", get(available_rooms))
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .route("/openapi.json", get(api::openapi))
+        .route("/branches", get(api::branches))
+        .route("/branches/:id/rooms", get(api::rooms))
+        .route("/branches/:id/rooms.ics", get(api::rooms_ics))
+        .route("/branches/:id/rooms/stream", get(api::rooms_stream))
+        .route("/branches/:id/rooms/latest", get(api::latest_rooms))
+        .route("/feeds", get(feeds::feeds))
+        .route("/feeds/:file", get(feeds::feed))
+        .route("/jobs", post(api::create_job))
+        .route("/jobs/:id", get(api::job).delete(api::cancel_job))
+        .layer(cors)
         .with_state(CCLSHBServerState {
-            web_driver_host: arcstr::literal!("localhost"),
-            web_driver_port: 4444,
+            scraper,
+            cache,
+            jobs,
+            readiness,
+            feeds,
+            default_group_size: config.default_group_size,
         });
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
-}
-
-#[debug_handler]
-async fn available_rooms(
-    State(state): State<CCLSHBServerState>,
-    // this argument tells axum to parse the request body
-    // as JSON into a `CreateUser` type
-    Json(payload): Json<AvailableRoomsArgs>,
-) -> Result<Json<Vec<(Room, Availability)>>, StatusCode> {
-    let AvailableRoomsArgs { date, group_size } = payload;
-    let CCLSHBServerState {
-        web_driver_host,
-        web_driver_port,
-    } = state;
-    let client = Client::new((web_driver_host.as_str(), web_driver_port))
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let v = client
-        .available_rooms(date, group_size)
+    let listener = match tokio::net::TcpListener::bind(config.listen_addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to listen on {}: {e}", config.listen_addr);
+            std::process::exit(1);
+        }
+    };
+    tracing::info!("Listening on {}", config.listen_addr);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok(Json(v))
+        .unwrap();
+
+    pool.close_idle().await;
 }
 
 #[cfg(test)]
@@ -61,25 +167,39 @@ mod tests {
         let day: chrono::NaiveDate = today + chrono::Duration::try_days(7).unwrap();
         let group_size = 10;
 
-        let args = crate::AvailableRoomsArgs {
+        let query = crate::api::RoomsQuery {
             date: day,
-            group_size,
+            group_size: Some(group_size),
         };
 
         let client = reqwest::Client::new();
         let resp = client
-            .get("http://localhost:3000/available_rooms")
-            .json(&args)
+            .get(format!(
+                "http://localhost:3000/branches/{}/rooms",
+                crate::api::CENTRAL_LIBRARY_BRANCH_ID
+            ))
+            .query(&query)
             .send()
             .await
             .unwrap();
-        let resp: Vec<(
+        let resp: serde_json::Value = resp.json().await.unwrap();
+        println!("Scraped at {}", resp["scraped_at"]);
+        let rooms: Vec<(
             calgary_central_library::Room,
             calgary_central_library::Availability,
-        )> = resp.json().await.unwrap();
+        )> = serde_json::from_value(resp["rooms"].clone()).unwrap();
 
-        for (room, availability) in resp.iter() {
+        for (room, availability) in rooms.iter() {
             println!("{:?} {}", room, availability);
         }
     }
+
+    #[test]
+    fn openapi_document_describes_the_rooms_endpoint() {
+        use utoipa::OpenApi;
+
+        let doc = crate::api::ApiDoc::openapi();
+        assert!(doc.paths.paths.contains_key("/branches/{id}/rooms"));
+        assert!(doc.paths.paths.contains_key("/branches/{id}/rooms.ics"));
+    }
 }
@@ -0,0 +1,297 @@
+use std::{
+    future::Future,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use calgary_central_library::{AsyncClient, Client, WebDriverEndpoint};
+use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// The page that idle sessions are parked on and that is used to check their health.
+const HEALTH_CHECK_URL: &str = "about:blank";
+
+#[derive(Clone)]
+pub(crate) struct PoolConfig {
+    /// New sessions are created on these WebDriver servers in turn.
+    pub(crate) web_drivers: Vec<WebDriverEndpoint>,
+    /// The maximum number of WebDriver sessions that can be open at the same time.
+    pub(crate) max_size: usize,
+    /// The number of requests a session serves before it is closed and replaced.
+    pub(crate) max_uses: u32,
+    /// How long a request waits in the queue for a free session.
+    pub(crate) acquire_timeout: Duration,
+    /// How long a health check of an idle session may take.
+    pub(crate) health_check_timeout: Duration,
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum AcquireError {
+    /// Every session stayed busy for longer than [`PoolConfig::acquire_timeout`].
+    #[error("Timed out waiting for a free WebDriver session")]
+    Timeout,
+    /// A new session could not be created.
+    #[error("Failed to create a new WebDriver session: {0}")]
+    New(NewError),
+}
+
+type NewError = <Client as AsyncClient>::NewError;
+
+/// What the pool needs of a WebDriver session. The futures are `Send`, unlike the ones of
+/// [`AsyncClient`], so that sessions can be closed in the background, and tests use a fake one.
+pub(crate) trait PoolClient: Sized + Send + Sync + 'static {
+    fn connect(
+        web_driver: &WebDriverEndpoint,
+    ) -> impl Future<Output = Result<Self, NewError>> + Send;
+    /// Whether the session still responds, leaving it on [`HEALTH_CHECK_URL`].
+    fn is_healthy(&self) -> impl Future<Output = bool> + Send;
+    fn disconnect(self) -> impl Future<Output = ()> + Send;
+}
+
+struct PooledClient<C> {
+    client: C,
+    uses: u32,
+}
+
+/// A bounded pool of reusable WebDriver sessions.
+///
+/// Requests that arrive while every session is busy wait in a FIFO queue
+/// (the one of [`tokio::sync::Semaphore`]) until a session is returned.
+pub(crate) struct ClientPool<C = Client> {
+    config: PoolConfig,
+    idle: Mutex<Vec<PooledClient<C>>>,
+    permits: Arc<Semaphore>,
+    next_web_driver: AtomicUsize,
+}
+
+/// A session checked out of the [`ClientPool`]. It goes back to the pool when dropped.
+pub(crate) struct PooledClientGuard<C: PoolClient = Client> {
+    pool: Arc<ClientPool<C>>,
+    client: Option<PooledClient<C>>,
+    broken: bool,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PoolClient for Client {
+    async fn connect(
+        WebDriverEndpoint { host, port }: &WebDriverEndpoint,
+    ) -> Result<Self, NewError> {
+        Client::new((host.as_str(), *port)).await
+    }
+
+    async fn is_healthy(&self) -> bool {
+        self.navigate_to_url(HEALTH_CHECK_URL).await.is_ok()
+    }
+
+    async fn disconnect(self) {
+        let _ = self.close().await;
+    }
+}
+
+impl<C: PoolClient> ClientPool<C> {
+    pub(crate) fn new(config: PoolConfig) -> Arc<Self> {
+        let permits = Arc::new(Semaphore::new(config.max_size));
+        Arc::new(Self {
+            config,
+            idle: Mutex::new(Vec::new()),
+            permits,
+            next_web_driver: AtomicUsize::new(0),
+        })
+    }
+
+    pub(crate) async fn acquire(self: &Arc<Self>) -> Result<PooledClientGuard<C>, AcquireError> {
+        self.acquire_within(self.config.acquire_timeout).await
+    }
+
+    /// Like [`ClientPool::acquire`], waiting at most `timeout` for a free session.
+    pub(crate) async fn acquire_within(
+        self: &Arc<Self>,
+        timeout: Duration,
+    ) -> Result<PooledClientGuard<C>, AcquireError> {
+        let permit = tokio::time::timeout(timeout, self.permits.clone().acquire_owned())
+            .await
+            .map_err(|_| AcquireError::Timeout)?
+            .expect("the semaphore of the pool is never closed");
+
+        let client = match self.take_healthy_idle().await {
+            Some(client) => client,
+            None => self.connect().await?,
+        };
+
+        Ok(PooledClientGuard {
+            pool: self.clone(),
+            client: Some(client),
+            broken: false,
+            _permit: permit,
+        })
+    }
+
+    async fn connect(&self) -> Result<PooledClient<C>, AcquireError> {
+        let web_drivers = &self.config.web_drivers;
+        let index = self.next_web_driver.fetch_add(1, Ordering::Relaxed) % web_drivers.len();
+        let web_driver = &web_drivers[index];
+        tracing::debug!("Creating a new WebDriver session on {web_driver}");
+        let client = C::connect(web_driver).await.map_err(AcquireError::New)?;
+        Ok(PooledClient { client, uses: 0 })
+    }
+
+    async fn take_healthy_idle(&self) -> Option<PooledClient<C>> {
+        loop {
+            let candidate = self.idle.lock().unwrap().pop()?;
+            let health_check = tokio::time::timeout(
+                self.config.health_check_timeout,
+                candidate.client.is_healthy(),
+            )
+            .await;
+            match health_check {
+                Ok(true) => return Some(candidate),
+                _ => {
+                    tracing::debug!("Replacing a WebDriver session that failed the health check");
+                    close_in_background(candidate.client)
+                }
+            }
+        }
+    }
+
+    fn release(&self, mut client: PooledClient<C>, broken: bool) {
+        client.uses += 1;
+        if broken || client.uses >= self.config.max_uses {
+            close_in_background(client.client);
+        } else {
+            self.idle.lock().unwrap().push(client);
+        }
+    }
+
+    /// Closes every idle session. Meant to be called on shutdown.
+    pub(crate) async fn close_idle(&self) {
+        let idle = std::mem::take(&mut *self.idle.lock().unwrap());
+        for PooledClient { client, .. } in idle {
+            client.disconnect().await;
+        }
+    }
+}
+
+fn close_in_background(client: impl PoolClient) {
+    tokio::spawn(client.disconnect());
+}
+
+impl<C: PoolClient> PooledClientGuard<C> {
+    /// Marks the session as unusable so that it is closed instead of being returned to the pool.
+    pub(crate) fn mark_broken(&mut self) {
+        self.broken = true;
+    }
+}
+
+impl<C: PoolClient> Deref for PooledClientGuard<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client.as_ref().unwrap().client
+    }
+}
+
+impl<C: PoolClient> Drop for PooledClientGuard<C> {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            self.pool.release(client, self.broken);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    /// The sessions that the [`FakeClient`]s of the current test opened and closed.
+    #[derive(Default)]
+    struct FakeWebDriver {
+        opened: AtomicUsize,
+        closed: AtomicUsize,
+    }
+
+    thread_local! {
+        // Every test runs on its own thread, with its own web driver
+        static WEB_DRIVER: RefCell<Arc<FakeWebDriver>> = RefCell::default();
+    }
+
+    struct FakeClient {
+        id: usize,
+        web_driver: Arc<FakeWebDriver>,
+    }
+
+    impl PoolClient for FakeClient {
+        async fn connect(_: &WebDriverEndpoint) -> Result<Self, NewError> {
+            let web_driver = WEB_DRIVER.with_borrow(Arc::clone);
+            let id = web_driver.opened.fetch_add(1, Ordering::SeqCst);
+            Ok(Self { id, web_driver })
+        }
+
+        async fn is_healthy(&self) -> bool {
+            true
+        }
+
+        async fn disconnect(self) {
+            self.web_driver.closed.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn pool(max_size: usize, max_uses: u32) -> Arc<ClientPool<FakeClient>> {
+        ClientPool::new(PoolConfig {
+            web_drivers: vec!["localhost:4444".parse().unwrap()],
+            max_size,
+            max_uses,
+            acquire_timeout: Duration::from_secs(10),
+            health_check_timeout: Duration::from_secs(1),
+        })
+    }
+
+    fn closed() -> usize {
+        WEB_DRIVER.with_borrow(|web_driver| web_driver.closed.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn requests_wait_for_a_returned_session() {
+        let pool = pool(1, 50);
+        let busy = pool.acquire().await.unwrap();
+        let queued = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.acquire().await.map(|client| client.id) }
+        });
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert!(!queued.is_finished());
+        assert!(matches!(
+            pool.acquire_within(Duration::from_secs(1)).await,
+            Err(AcquireError::Timeout)
+        ));
+
+        drop(busy);
+        // The queued request gets the same session instead of a new one
+        assert_eq!(queued.await.unwrap().unwrap(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sessions_are_replaced_after_max_uses() {
+        let pool = pool(1, 2);
+        for _ in 0..2 {
+            assert_eq!(pool.acquire().await.unwrap().id, 0);
+        }
+        assert_eq!(pool.acquire().await.unwrap().id, 1);
+        tokio::task::yield_now().await;
+        assert_eq!(closed(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn broken_sessions_are_closed() {
+        let pool = pool(1, 50);
+        pool.acquire().await.unwrap().mark_broken();
+        assert_eq!(pool.acquire().await.unwrap().id, 1);
+        tokio::task::yield_now().await;
+        assert_eq!(closed(), 1);
+    }
+}
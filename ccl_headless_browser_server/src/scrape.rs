@@ -0,0 +1,76 @@
+use std::{sync::Arc, time::Duration};
+
+use calgary_central_library::{
+    AsyncClient, Availability, Client, Room, ScrapeProgress, Snapshot, SnapshotStore,
+    SqliteSnapshotStore,
+};
+use chrono::{NaiveDate, Utc};
+use thiserror::Error;
+
+use crate::pool::{AcquireError, ClientPool};
+
+#[derive(Error, Debug)]
+pub(crate) enum ScrapeError {
+    #[error(transparent)]
+    Acquire(#[from] AcquireError),
+    #[error("Failed to list the available rooms: {0}")]
+    AvailableRooms(<Client as AsyncClient>::AvailableRoomsError),
+    #[error("Listing the available rooms took longer than {0:?}")]
+    Timeout(Duration),
+}
+
+pub(crate) struct Scraper {
+    pub(crate) pool: Arc<ClientPool>,
+    /// How long scraping the booking page may take once a session has been acquired.
+    pub(crate) timeout: Duration,
+    /// Where successful scrapes are recorded, if anywhere.
+    pub(crate) snapshots: Option<Arc<SqliteSnapshotStore>>,
+}
+
+impl Scraper {
+    /// Scrapes the available rooms with a session from the pool.
+    ///
+    /// The session is recycled if the scrape fails. Successful scrapes are recorded as snapshots.
+    pub(crate) async fn available_rooms(
+        &self,
+        date: NaiveDate,
+        group_size: u8,
+        on_progress: impl FnMut(ScrapeProgress<'_>),
+    ) -> Result<Vec<(Room, Availability)>, ScrapeError> {
+        let mut client = self.pool.acquire().await?;
+        let result = tokio::time::timeout(
+            self.timeout,
+            client.available_rooms_with_progress(date, group_size, on_progress),
+        )
+        .await;
+        let e = match result {
+            Ok(Ok(rooms)) => {
+                self.record(date, group_size, &rooms);
+                return Ok(rooms);
+            }
+            Ok(Err(e)) => ScrapeError::AvailableRooms(e),
+            Err(_) => ScrapeError::Timeout(self.timeout),
+        };
+        client.mark_broken();
+        tracing::warn!("Failed to scrape the rooms for {date} and {group_size} people: {e}");
+        Err(e)
+    }
+
+    /// Saves a snapshot of the rooms in the background.
+    fn record(&self, date: NaiveDate, group_size: u8, rooms: &[(Room, Availability)]) {
+        let Some(snapshots) = self.snapshots.clone() else {
+            return;
+        };
+        let snapshot = Snapshot {
+            date,
+            group_size,
+            scraped_at: Utc::now(),
+            rooms: rooms.to_vec(),
+        };
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = snapshots.save(&snapshot) {
+                tracing::warn!("Failed to record the snapshot for {date}: {e}");
+            }
+        });
+    }
+}
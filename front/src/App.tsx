@@ -1,12 +1,19 @@
-import { useState } from 'react';
+import { useEffect, useState } from 'react';
 import Calendar from './Calendar';
 import Header from './Header';
 import "./index.css";
-import { Settings, DEFAULT_SETTINGS } from './external';
+import { Settings, DEFAULT_SETTINGS, loadDefaultAttendance } from './external';
 
 function App() {
   const [getSettings, setSettings] = useState<Settings>(DEFAULT_SETTINGS);
 
+  // The rooms are checked for the group size of the profile until another one is chosen
+  useEffect(() => {
+    loadDefaultAttendance().then((attendance) => {
+      setSettings((settings) => ({ ...settings, attendance }));
+    }).catch((error) => console.error(error));
+  }, []);
+
   return (
     <>
       <Header getSettings={getSettings} setSettings={setSettings} />
@@ -1,7 +1,7 @@
-import { useState } from 'react';
+import { useEffect, useState } from 'react';
 import { Calendar as RSCalendar } from 'rsuite';
 import 'rsuite/Calendar/styles/index.css';
-import { Settings, getAvailabilityForDay } from './external';
+import { DayProgress, Settings, advanceProgress, getAvailabilityForDay, isoDate, onScrapeProgress } from './external';
 
 type CalendarProps = {
     getSettings: Settings;
@@ -9,12 +9,35 @@ type CalendarProps = {
 
 function Calendar({ getSettings }: CalendarProps) {
   const [dates, setDates] = useState(new Map());
+  // Keyed by ISO date, the way the `scrape` events name the days
+  const [progress, setProgress] = useState(new Map<string, DayProgress>());
+
+  useEffect(() => {
+    const unlisten = onScrapeProgress(({ date, group_size, stage }) => {
+      if (group_size !== getSettings.attendance) {
+        return;
+      }
+      setProgress((progress) => new Map(progress).set(date, advanceProgress(progress.get(date), stage)));
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, [getSettings.attendance]);
 
   function renderCell(date: Date): React.ReactNode {
-    if (dates.has(date.getTime())) {
-      return <><s>Booked</s></>
+    if (!dates.has(date.getTime())) {
+      return null
+    }
+    const day = progress.get(isoDate(date));
+    if (day && day.finished === null && day.total !== null) {
+      // The rooms that are free so far, while the others are still being checked
+      const free = day.rooms.filter(([, availability]) => availability.length > 0).length;
+      return <>{free} free ({day.rooms.length}/{day.total} checked)</>
+    }
+    if (day?.finished === null) {
+      return <>Checking…</>
     }
-    return null
+    return <><s>Booked</s></>
   }
 
   function onSelect(date: Date) {
@@ -24,9 +47,8 @@ function Calendar({ getSettings }: CalendarProps) {
     if (dates.has(time)) {
       dates.delete(time);
     } else {
-        const source = getSettings.libraryDataSource;
         const groupSize = getSettings.attendance;
-        const data = getAvailabilityForDay(source, date, groupSize);
+        const data = getAvailabilityForDay(date, groupSize);
         console.log(data);
         data?.then((value) => {
           console.log(value);
@@ -10,7 +10,7 @@ type HeaderProps = {
     setSettings: Dispatch<SetStateAction<Settings>>;
 };
 
-function Header({getSettings}: HeaderProps) {
+function Header({getSettings, setSettings}: HeaderProps) {
     return (<>
             <div id="header">
                 <h1>Calgary Rust Room Booking</h1>
@@ -37,7 +37,7 @@ function Header({getSettings}: HeaderProps) {
                 } />
                 <dialog id="settings-dialog">
                     <div id="settings-dialog-header"></div>
-                    <SettingsDialogBody/>
+                    <SettingsDialogBody getSettings={getSettings} setSettings={setSettings} />
                 </dialog>
             </div>
         </>
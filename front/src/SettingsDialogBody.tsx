@@ -1,29 +1,47 @@
-import { /*Settings,*/ DEFAULT_SETTINGS } from "./external";
-// import { Dispatch, SetStateAction } from 'react';
-
-
-//type SettingsDialogBodyProps = {
-//    getSettings: Settings;
-//    setSettings: Dispatch<SetStateAction<Settings>>;
-//};
-
-function SettingsDialogBody(/*{getSettings, setSettings}: SettingsDialogBodyProps*/): JSX.Element {
-    function updateToggleStateForWebdriverInputs() {
-        const dataSourceSelect = document.getElementById("library-data-source") as HTMLSelectElement;
-        const dataSource = dataSourceSelect.value;
-        if (dataSource === "crawling_server") {
-            document.getElementById("webdriver-host")!.removeAttribute("disabled");
-            document.getElementById("webdriver-port")!.removeAttribute("disabled")
-        } else {
-            document.getElementById("webdriver-host")!.setAttribute("disabled", "true");
-            document.getElementById("webdriver-port")!.setAttribute("disabled", "true");
-        }
+import { Dispatch, SetStateAction, useEffect, useState } from 'react';
+import { Settings, LibraryDataSource, loadLibraryDataSource, saveLibraryDataSource } from "./external";
+
+// Where `ccl_headless_browser_server` listens unless it's configured otherwise
+const DEFAULT_CRAWLING_SERVER = { host: "localhost", port: 3000 };
+
+type SettingsDialogBodyProps = {
+    getSettings: Settings;
+    setSettings: Dispatch<SetStateAction<Settings>>;
+};
+
+function SettingsDialogBody({getSettings, setSettings}: SettingsDialogBodyProps): JSX.Element {
+    // Nothing is saved before the saved source is known, so that it isn't overwritten with the defaults
+    const [loaded, setLoaded] = useState(false);
+    // Kept while the Tauri IPC is chosen, so that choosing the crawling server again restores it
+    const [server, setServer] = useState(DEFAULT_CRAWLING_SERVER);
+
+    useEffect(() => {
+        loadLibraryDataSource().then((libraryDataSource) => {
+            if (libraryDataSource.kind === "crawling_server") {
+                setServer({ host: libraryDataSource.host, port: libraryDataSource.port });
+            }
+            setSettings((settings) => ({ ...settings, libraryDataSource }));
+            setLoaded(true);
+        }).catch((error) => console.error(error));
+    }, [setSettings]);
+
+    // The desktop app keeps the source between runs and checks the rooms through it
+    function choose(libraryDataSource: LibraryDataSource) {
+        setSettings((settings) => ({ ...settings, libraryDataSource }));
+        saveLibraryDataSource(libraryDataSource).catch((error) => console.error(error));
+    }
+
+    function chooseServer() {
+        choose({ kind: "crawling_server", ...server });
     }
 
+    const usesServer = getSettings.libraryDataSource.kind === "crawling_server";
+
     return (<>
             <div>
                 <label htmlFor="attendance">Attendance: </label>
-                <input type="number" id="attendance" list="attendance-options" min="5" max="100" defaultValue={DEFAULT_SETTINGS.attendance.toString()} />
+                <input type="number" id="attendance" list="attendance-options" min="5" max="100" value={getSettings.attendance}
+                    onChange={(event) => setSettings((settings) => ({ ...settings, attendance: Number(event.target.value) }))} />
                 <datalist id="attendance-options">
                     <option value="10" />
                     <option value="16" />
@@ -32,23 +50,27 @@ function SettingsDialogBody(/*{getSettings, setSettings}: SettingsDialogBodyProp
             </div>
             <div>
                 <label htmlFor="library-data-source">Library data source: </label>
-                <select id="library-data-source" onChange={updateToggleStateForWebdriverInputs} defaultValue={DEFAULT_SETTINGS.libraryDataSource.kind}>
+                <select id="library-data-source" disabled={!loaded} value={getSettings.libraryDataSource.kind} onChange={
+                    (event) => event.target.value === "crawling_server" ? chooseServer() : choose({ kind: "tauri_webview" })
+                }>
                     <option value="tauri_webview">Tauri IPC (command)</option>
                     <option value="crawling_server">Crawling WebDriver server</option>
                 </select>
             </div>
             <div>
-                <label htmlFor="webdriver-host">WebDriver host: </label>
-                <input id="webdriver-host" disabled={true} list="webdriver-host-options" defaultValue="localhost" />
-                <datalist id="webdriver-host-options">
+                <label htmlFor="crawling-server-host">Crawling server host: </label>
+                <input id="crawling-server-host" disabled={!loaded || !usesServer} list="crawling-server-host-options" value={server.host}
+                    onChange={(event) => setServer({ ...server, host: event.target.value })} onBlur={chooseServer} />
+                <datalist id="crawling-server-host-options">
                     <option value="localhost" />
                 </datalist>
             </div>
             <div>
-                <label htmlFor="webdriver-port">WebDriver port: </label>
-                <input type="number" id="webdriver-port" disabled={true} list="webdriver-port-options" min="0" max="65535" defaultValue="4444" />
-                <datalist id="webdriver-port-options">
-                    <option value="4444" />
+                <label htmlFor="crawling-server-port">Crawling server port: </label>
+                <input type="number" id="crawling-server-port" disabled={!loaded || !usesServer} list="crawling-server-port-options" min="0" max="65535" value={server.port}
+                    onChange={(event) => setServer({ ...server, port: Number(event.target.value) })} onBlur={chooseServer} />
+                <datalist id="crawling-server-port-options">
+                    <option value="3000" />
                 </datalist>
             </div>
         </>
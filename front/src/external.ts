@@ -1,40 +1,79 @@
-import { Room, Availability, availableRooms } from "../../tauri-app/bindings/bindings"
+import { Room, Availability, LibraryDataSource, ScrapeEvent, ScrapeStage, availableRooms, defaultGroupSize, libraryDataSource, setLibraryDataSource } from "../../tauri-app/bindings/bindings"
 
-type CrawlingServer =  {
-  kind: "crawling_server",
-  host: string,
-  port: number,
-}
-
-type TauriWebview = {
-  kind: "tauri_webview",
+// The parts of the global Tauri API (`withGlobalTauri`) that are used here
+declare global {
+  interface Window {
+    __TAURI__: {
+      event: {
+        listen<T>(event: string, handler: (event: { payload: T }) => void): Promise<() => void>,
+      },
+    },
+  }
 }
 
-type LibraryDataSource = CrawlingServer | TauriWebview;
-
 type Settings = {
   attendance: number,
   libraryDataSource: LibraryDataSource,
 };
 
-export function getAvailabilityForDay(source: LibraryDataSource, date: Date, groupSize: number): Promise<([Room, Availability])[]> | null {
-  const delta = Math.round((date.getTime() - new Date().getTime()) / (1000 * 3600 * 24));
-  if (delta < 0) {
-    console.log("Calling getAvailabilityForDay() with a date in the past");
-    return null;
-  }
-  switch (source.kind) {
-    case "tauri_webview":
-      return availableRooms(delta, groupSize);
-    case "crawling_server":
-      console.log("Calling getAvailabilityForDay() with a crawling_server");
-      break;
-  }
-  // the difference in days from today to the date
-  
-  console.log("delta =", delta);
+// The local day of the date, e.g. `2026-11-12`, which is what the commands and the crawling server expect
+export function isoDate(date: Date): string {
+  return `${date.getFullYear()}-${String(date.getMonth() + 1).padStart(2, "0")}-${String(date.getDate()).padStart(2, "0")}`;
+}
+
+// The commands reject with a `CommandError`, e.g. `{ kind: "past_date", ... }` for a day that has passed.
+// The rooms are checked through the saved library data source, so no chromedriver is needed with a crawling server.
+export function getAvailabilityForDay(date: Date, groupSize: number): Promise<([Room, Availability])[]> {
+  return availableRooms(isoDate(date), groupSize);
+}
+
+// The group size of the default profile in the configuration that the app shares with the command-line app
+export function loadDefaultAttendance(): Promise<number> {
+  return defaultGroupSize();
+}
 
-  return null;
+// The library data source that the app saved in its settings, `tauri_webview` until another one is chosen
+export function loadLibraryDataSource(): Promise<LibraryDataSource> {
+  return libraryDataSource();
+}
+
+// Saves the library data source in the settings of the app, where the commands read it
+export function saveLibraryDataSource(source: LibraryDataSource): Promise<null> {
+  return setLibraryDataSource(source);
+}
+
+// Calls `handler` with every step of checking the rooms through Tauri, e.g. to show the rooms as they are parsed.
+// The returned promise resolves to a function that stops listening.
+export function onScrapeProgress(handler: (event: ScrapeEvent) => void): Promise<() => void> {
+  // The name of the event is `SCRAPE_EVENT` in `tauri-app/src-tauri/src/progress.rs`
+  return window.__TAURI__.event.listen<ScrapeEvent>("scrape", (event) => handler(event.payload));
+}
+
+// The rooms of a day as they are parsed, put together from its `scrape` events
+type DayProgress = {
+  total: number | null,
+  rooms: ([Room, Availability])[],
+  finished: "done" | "failed" | null,
+};
+
+const NO_PROGRESS: DayProgress = { total: null, rooms: [], finished: null };
+
+// The progress of a day after `stage`. A restart drops the rooms parsed so far, since they are reported again.
+export function advanceProgress(progress: DayProgress | undefined, stage: ScrapeStage): DayProgress {
+  const current = progress ?? NO_PROGRESS;
+  switch (stage.kind) {
+    case "connecting":
+    case "restarted":
+      return NO_PROGRESS;
+    case "page_loaded":
+      return { ...current, total: stage.total };
+    case "room_parsed":
+      return { ...current, total: stage.total, rooms: [...current.rooms, [stage.room, stage.availability]] };
+    case "done":
+      return { ...current, finished: "done" };
+    case "failed":
+      return { ...current, finished: "failed" };
+  }
 }
 
 export const DEFAULT_SETTINGS: Settings = {
@@ -43,4 +82,4 @@ export const DEFAULT_SETTINGS: Settings = {
 };
 
 
-export type { Settings };
+export type { Settings, LibraryDataSource, DayProgress };
@@ -10,15 +10,93 @@ declare global {
 // Function avoids 'window not defined' in SSR
 const invoke = () => window.__TAURI_INVOKE__;
 
-export function availableRooms(daysFromToday: number, groupSize: number) {
-    return invoke()<([Room, Availability])[]>("available_rooms", { daysFromToday,groupSize })
+export function availableRooms(date: string, groupSize: number) {
+    return invoke()<([Room, Availability])[]>("available_rooms", { date,groupSize })
 }
 
-export type TimeSlot = number
-export type KnownRoom = "R205AMeetingRoom" | "R205BMeetingRoom" | "R205CMeetingRoom" | "R206ATerentiukSpaceForAdultLearning" | "R206BMillarFamilyLearningAndDiscoveryRoom" | "R320CMeetingRoom" | "R320GMeetingRoom" | "R320HMeetingRoom" | "R310AMeetingRoom" | "R310BMeetingRoom" | "R317AMeetingRoom" | "R317BFieldLawMeetingRoom" | "R319CMeetingRoom" | "R320AIdeaLab" | "R316B"
+/**
+ * The rooms of every day from `from` to `to`, included.
+ */
+export function availableRoomsBetween(from: string, to: string, groupSize: number) {
+    return invoke()<Snapshot[]>("available_rooms_between", { from,to,groupSize })
+}
+
+/**
+ * The most recently recorded rooms for the day, ranked like the scraped ones.
+ */
+export function lastKnownRooms(date: string, groupSize: number) {
+    return invoke()<Snapshot | null>("last_known_rooms", { date,groupSize })
+}
+
+/**
+ * The expected number of attendees of the default profile, until another one is chosen.
+ */
+export function defaultGroupSize() {
+    return invoke()<number>("default_group_size")
+}
+
+/**
+ * Where the rooms are checked, as it was last chosen.
+ */
+export function libraryDataSource() {
+    return invoke()<LibraryDataSource>("library_data_source")
+}
+
+/**
+ * Checks the rooms through `source` from now on, including after a restart of the app.
+ */
+export function setLibraryDataSource(source: LibraryDataSource) {
+    return invoke()<null>("set_library_data_source", { source })
+}
+
+/**
+ * The WebDriver server of the app, without connecting to it or waiting for a running check.
+ */
+export function webDriverConnection() {
+    return invoke()<ConnectionStatus>("web_driver_connection")
+}
+
+/**
+ * Connects to the WebDriver server unless the open session still responds.
+ */
+export function checkWebDriverConnection() {
+    return invoke()<ConnectionStatus>("check_web_driver_connection")
+}
+
+/**
+ * Connects to another WebDriver server, e.g. one that was started on another port.
+ */
+export function setWebDriver(host: string, port: number) {
+    return invoke()<ConnectionStatus>("set_web_driver", { host,port })
+}
+
+export type ScrapeStage = { kind: "connecting" } | { kind: "page_loaded"; total: number } | { kind: "room_parsed"; index: number; total: number; room: Room; availability: Availability } | { kind: "restarted" } | { kind: "done" } | { kind: "failed"; error: CommandError }
+/**
+ * A step of checking the rooms of a day for a group, emitted to the window that asked for them.
+ */
+export type ScrapeEvent = { date: string; group_size: number; stage: ScrapeStage }
 export type Availability = TimeSlot[]
+/**
+ * The WebDriver server of the app and whether a session is open on it.
+ */
+export type ConnectionStatus = { host: string; port: number; connected: boolean }
+export type Room = { choice: RoomChoice; title: string; description: string; inferred_capacity: number | null }
+/**
+ * Why a command failed, so that the front end can tell the cases apart.
+ */
+export type CommandError = { kind: "past_date"; date: string; today: string } | { kind: "backward_range"; from: string; to: string } | { kind: "range_too_long"; max_days: number } | { kind: "web_driver_unreachable"; host: string; port: number; message: string } | { kind: "web_driver_connection_lost"; message: string } | { kind: "navigation_failed"; message: string } | { kind: "page_structure_changed"; message: string } | { kind: "scrape_failed"; message: string } | { kind: "server_unreachable"; message: string } | { kind: "server_error"; status: number; code: string; message: string; retryable: boolean } | { kind: "database"; message: string } | { kind: "settings"; message: string }
+/**
+ * The rooms that were available for a group of a given size on a given day,
+ * as seen at `scraped_at`.
+ */
+export type Snapshot = { date: string; group_size: number; scraped_at: string; rooms: ([Room, Availability])[] }
+/**
+ * Where the desktop app checks the rooms.
+ */
+export type LibraryDataSource = { kind: "tauri_webview" } | { kind: "crawling_server"; host: string; port: number }
+export type KnownRoom = "R205AMeetingRoom" | "R205BMeetingRoom" | "R205CMeetingRoom" | "R206ATerentiukSpaceForAdultLearning" | "R206BMillarFamilyLearningAndDiscoveryRoom" | "R320CMeetingRoom" | "R320GMeetingRoom" | "R320HMeetingRoom" | "R310AMeetingRoom" | "R310BMeetingRoom" | "R317AMeetingRoom" | "R317BFieldLawMeetingRoom" | "R319CMeetingRoom" | "R320AIdeaLab" | "R316B"
 /**
  * Either a specific room or an unknown room.
  */
 export type RoomChoice = { KnownRoom: KnownRoom } | "UnknownRoom"
-export type Room = { choice: RoomChoice; title: string; description: string; inferred_capacity: number | null }
+export type TimeSlot = number
@@ -0,0 +1,150 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use calgary_central_library::{
+    AsyncClient, Availability, Client, Room, ScrapeProgress, WebDriverEndpoint,
+};
+use chrono::NaiveDate;
+use serde::Serialize;
+use tauri::async_runtime::Mutex;
+
+use crate::error::CommandError;
+
+/// The page that the session is sent to when its health is checked.
+const HEALTH_CHECK_URL: &str = "about:blank";
+
+/// The WebDriver session that the commands share. It is opened by the first command
+/// that needs it, opened again when it's lost and closed when the app exits.
+///
+/// The endpoint and whether a session is open are kept outside of the session's lock,
+/// so that [`Connection::status`] answers right away while a scrape holds the session.
+pub(crate) struct Connection {
+    endpoint: std::sync::Mutex<WebDriverEndpoint>,
+    connected: AtomicBool,
+    client: Mutex<Option<Client>>,
+}
+
+/// The WebDriver server of the app and whether a session is open on it.
+#[derive(Serialize, specta::Type)]
+pub(crate) struct ConnectionStatus {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) connected: bool,
+}
+
+impl Connection {
+    pub(crate) fn new(endpoint: WebDriverEndpoint) -> Self {
+        Self {
+            endpoint: std::sync::Mutex::new(endpoint),
+            connected: AtomicBool::new(false),
+            client: Mutex::new(None),
+        }
+    }
+
+    /// The endpoint and whether a session is open, without waiting for the session.
+    pub(crate) fn status(&self) -> ConnectionStatus {
+        let WebDriverEndpoint { host, port } = self.endpoint.lock().unwrap().clone();
+        ConnectionStatus {
+            host,
+            port,
+            connected: self.connected.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Replaces the session in `slot`, keeping [`Connection::status`] up to date.
+    fn set(&self, slot: &mut Option<Client>, client: Option<Client>) {
+        self.connected.store(client.is_some(), Ordering::Relaxed);
+        *slot = client;
+    }
+
+    /// The open session in `slot`, or a new one.
+    async fn client<'a>(&self, slot: &'a mut Option<Client>) -> Result<&'a Client, CommandError> {
+        if slot.is_none() {
+            let endpoint = self.endpoint.lock().unwrap().clone();
+            let WebDriverEndpoint { host, port } = &endpoint;
+            let client = Client::new((host.as_str(), *port))
+                .await
+                .map_err(|e| CommandError::web_driver_unreachable(&endpoint, e))?;
+            self.set(slot, Some(client));
+        }
+        Ok(slot.as_ref().unwrap())
+    }
+
+    async fn disconnect(&self, slot: &mut Option<Client>) {
+        if let Some(client) = slot.take() {
+            self.connected.store(false, Ordering::Relaxed);
+            if let Err(e) = client.close().await {
+                eprintln!("Failed to close the WebDriver session: {e}");
+            }
+        }
+    }
+
+    /// Opens a session unless one is open and still responds.
+    pub(crate) async fn check(&self) -> Result<ConnectionStatus, CommandError> {
+        let mut slot = self.client.lock().await;
+        let healthy = match &*slot {
+            Some(client) => client.navigate_to_url(HEALTH_CHECK_URL).await.is_ok(),
+            None => false,
+        };
+        if !healthy {
+            // A session that doesn't respond can't be closed either
+            self.set(&mut slot, None);
+            self.client(&mut slot).await?;
+        }
+        Ok(self.status())
+    }
+
+    /// Closes the session on the previous WebDriver server and opens one on `endpoint`.
+    pub(crate) async fn reconnect(
+        &self,
+        endpoint: WebDriverEndpoint,
+    ) -> Result<ConnectionStatus, CommandError> {
+        let mut slot = self.client.lock().await;
+        self.disconnect(&mut slot).await;
+        *self.endpoint.lock().unwrap() = endpoint;
+        self.client(&mut slot).await?;
+        Ok(self.status())
+    }
+
+    /// Scrapes the rooms, once more with a new session if the open one turns out to be lost,
+    /// e.g. because the WebDriver server was restarted. `on_restart` is called before
+    /// the second attempt, whose progress starts over.
+    pub(crate) async fn available_rooms(
+        &self,
+        date: NaiveDate,
+        group_size: u8,
+        mut on_progress: impl FnMut(ScrapeProgress<'_>),
+        mut on_restart: impl FnMut(),
+    ) -> Result<Vec<(Room, Availability)>, CommandError> {
+        let mut slot = self.client.lock().await;
+        let mut retry = slot.is_some();
+        loop {
+            let result = self
+                .client(&mut slot)
+                .await?
+                .available_rooms_with_progress(date, group_size, &mut on_progress)
+                .await;
+            match result {
+                Ok(rooms) => return Ok(rooms),
+                Err(e) if e.is_web_driver_connection_lost() => {
+                    self.set(&mut slot, None);
+                    if !retry {
+                        return Err(CommandError::scrape(e));
+                    }
+                    retry = false;
+                    on_restart();
+                }
+                Err(e) => return Err(CommandError::scrape(e)),
+            }
+        }
+    }
+
+    /// Ends the session, if any, unless a scrape still holds it. Quitting doesn't wait for
+    /// the scrape then, and the WebDriver server drops the session once it times out.
+    pub(crate) async fn close(&self) {
+        let Ok(mut slot) = self.client.try_lock() else {
+            eprintln!("A scrape is still running, so its WebDriver session is left to time out");
+            return;
+        };
+        self.disconnect(&mut slot).await;
+    }
+}
@@ -0,0 +1,89 @@
+use calgary_central_library::{AsyncClient, Client, RemoteError, WebDriverEndpoint};
+use chrono::NaiveDate;
+use serde::Serialize;
+use thiserror::Error;
+
+/// Why a command failed, so that the front end can tell the cases apart.
+#[derive(Error, Debug, Clone, Serialize, specta::Type)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum CommandError {
+    #[error("{date} has already passed, today is {today}")]
+    PastDate { date: NaiveDate, today: NaiveDate },
+    #[error("The range ends on {to}, before it starts on {from}")]
+    BackwardRange { from: NaiveDate, to: NaiveDate },
+    #[error("A range can't be longer than {max_days} days")]
+    RangeTooLong { max_days: u16 },
+    #[error("Failed to connect to the WebDriver server at {host}:{port}: {message}")]
+    WebDriverUnreachable {
+        host: String,
+        port: u16,
+        message: String,
+    },
+    #[error("The connection to the WebDriver server was lost: {message}")]
+    WebDriverConnectionLost { message: String },
+    #[error("The booking page could not be loaded: {message}")]
+    NavigationFailed { message: String },
+    #[error("The booking page has changed and can't be read anymore: {message}")]
+    PageStructureChanged { message: String },
+    #[error("Failed to check the rooms: {message}")]
+    ScrapeFailed { message: String },
+    #[error("Failed to reach the crawling server: {message}")]
+    ServerUnreachable { message: String },
+    /// The error body of the crawling server, e.g. with the code `web_driver_unreachable`.
+    #[error("The crawling server responded with {status} ({code}): {message}")]
+    ServerError {
+        status: u16,
+        code: String,
+        message: String,
+        retryable: bool,
+    },
+    #[error("Failed to read the recorded rooms: {message}")]
+    Database { message: String },
+    #[error("Failed to save the settings to {message}")]
+    Settings { message: String },
+}
+
+impl CommandError {
+    pub(crate) fn web_driver_unreachable(
+        endpoint: &WebDriverEndpoint,
+        e: <Client as AsyncClient>::NewError,
+    ) -> Self {
+        Self::WebDriverUnreachable {
+            host: endpoint.host.clone(),
+            port: endpoint.port,
+            message: e.to_string(),
+        }
+    }
+
+    pub(crate) fn server(e: RemoteError) -> Self {
+        match e {
+            RemoteError::Request(e) => Self::ServerUnreachable {
+                message: e.to_string(),
+            },
+            RemoteError::Api {
+                status,
+                code,
+                message,
+                retryable,
+            } => Self::ServerError {
+                status: status.as_u16(),
+                code,
+                message,
+                retryable,
+            },
+        }
+    }
+
+    pub(crate) fn scrape(e: <Client as AsyncClient>::AvailableRoomsError) -> Self {
+        let message = e.to_string();
+        if e.is_web_driver_connection_lost() {
+            Self::WebDriverConnectionLost { message }
+        } else if e.is_page_structure_changed() {
+            Self::PageStructureChanged { message }
+        } else if e.is_navigation_failed() {
+            Self::NavigationFailed { message }
+        } else {
+            Self::ScrapeFailed { message }
+        }
+    }
+}
@@ -1,49 +1,331 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use calgary_central_library::{AsyncClient, Availability, Client, Room};
+mod connection;
+mod error;
+mod progress;
+mod settings;
+
+use calgary_central_library::{
+    default_database_path, library_today, Availability, Config, Profile, RemoteError, Room,
+    Snapshot, SnapshotStore, SqliteSnapshotStore, WebDriverEndpoint,
+};
+use chrono::NaiveDate;
+use connection::{Connection, ConnectionStatus};
+use error::CommandError;
+use progress::{Progress, ScrapeStage};
+use settings::{LibraryDataSource, SettingsStore};
+use tauri::{Manager, RunEvent, Window};
+
+/// The longest range of days that can be checked at once.
+const MAX_RANGE_DAYS: u16 = 31;
+
+/// The database of past scrapes, if it could be opened.
+struct Snapshots(Option<SqliteSnapshotStore>);
+
+/// Fails if `date` has passed. The rooms of past days can't be booked anymore.
+fn upcoming(date: NaiveDate) -> Result<NaiveDate, CommandError> {
+    let today = library_today();
+    if date < today {
+        return Err(CommandError::PastDate { date, today });
+    }
+    Ok(date)
+}
+
+/// Checks the rooms through the chosen [`LibraryDataSource`], emitting every step to `window`,
+/// and records them if the database is available. The rooms are ranked by the `profile`.
+async fn scrape(
+    window: &Window,
+    connection: &Connection,
+    settings: &SettingsStore,
+    snapshots: &Snapshots,
+    profile: &Profile,
+    date: NaiveDate,
+    group_size: u8,
+) -> Result<Snapshot, CommandError> {
+    let progress = Progress::new(window.clone(), date, group_size);
+    progress.emit(ScrapeStage::Connecting);
+    let mut snapshot = match settings.library_data_source().remote_client() {
+        // The server doesn't report its progress, only the result
+        Some(server) => server
+            .available_rooms(date, group_size)
+            .await
+            .map_err(CommandError::server),
+        None => connection
+            .available_rooms(
+                date,
+                group_size,
+                |step| progress.scraped(step),
+                || progress.emit(ScrapeStage::Restarted),
+            )
+            .await
+            .map(|rooms| Snapshot {
+                date,
+                group_size,
+                scraped_at: chrono::Utc::now(),
+                rooms,
+            }),
+    }
+    .inspect_err(|error| {
+        progress.emit(ScrapeStage::Failed {
+            error: error.clone(),
+        })
+    })?;
+    progress.emit(ScrapeStage::Done);
+    if let Snapshots(Some(store)) = snapshots {
+        if let Err(e) = store.save(&snapshot) {
+            eprintln!("Failed to record the rooms: {e}");
+        }
+    }
+    profile.rank(&mut snapshot.rooms);
+    Ok(snapshot)
+}
 
 #[tauri::command]
 #[specta::specta]
 async fn available_rooms(
-    days_from_today: u8,
+    window: Window,
+    connection: tauri::State<'_, Connection>,
+    settings: tauri::State<'_, SettingsStore>,
+    snapshots: tauri::State<'_, Snapshots>,
+    profile: tauri::State<'_, Profile>,
+    date: NaiveDate,
+    group_size: u8,
+) -> Result<Vec<(Room, Availability)>, CommandError> {
+    let date = upcoming(date)?;
+    let snapshot = scrape(
+        &window,
+        &connection,
+        &settings,
+        &snapshots,
+        &profile,
+        date,
+        group_size,
+    )
+    .await?;
+    Ok(snapshot.rooms)
+}
+
+/// The rooms of every day from `from` to `to`, included.
+#[tauri::command]
+#[specta::specta]
+#[allow(clippy::too_many_arguments)]
+async fn available_rooms_between(
+    window: Window,
+    connection: tauri::State<'_, Connection>,
+    settings: tauri::State<'_, SettingsStore>,
+    snapshots: tauri::State<'_, Snapshots>,
+    profile: tauri::State<'_, Profile>,
+    from: NaiveDate,
+    to: NaiveDate,
+    group_size: u8,
+) -> Result<Vec<Snapshot>, CommandError> {
+    let from = upcoming(from)?;
+    if to < from {
+        return Err(CommandError::BackwardRange { from, to });
+    }
+    if (to - from).num_days() >= MAX_RANGE_DAYS.into() {
+        return Err(CommandError::RangeTooLong {
+            max_days: MAX_RANGE_DAYS,
+        });
+    }
+    let mut days = Vec::new();
+    for date in from.iter_days().take_while(|date| *date <= to) {
+        days.push(
+            scrape(
+                &window,
+                &connection,
+                &settings,
+                &snapshots,
+                &profile,
+                date,
+                group_size,
+            )
+            .await?,
+        );
+    }
+    Ok(days)
+}
+
+/// The most recently recorded rooms for the day, to be shown when they can't be scraped.
+/// Without any in the database of the app, the ones that the crawling server recorded, if it's used.
+async fn latest_snapshot(
+    settings: &SettingsStore,
+    snapshots: &Snapshots,
+    date: NaiveDate,
     group_size: u8,
-) -> Result<Vec<(Room, Availability)>, String> {
-    let days_from_today: i64 = days_from_today.try_into().unwrap();
-    let now = chrono::Local::now();
-    let today = now.date_naive();
-    let day = today
-        + chrono::Duration::try_days(days_from_today).ok_or(format!(
-            "Failed to create chorono::Duration from the value {days_from_today} of {var_name}",
-            var_name = stringify!(days_from_today)
-        ))?;
-
-    let chromedriver_addr = ("localhost", 4444);
-    let client = Client::new(chromedriver_addr)
-        .await
-        .map_err(|e| e.to_string())?;
-    let rooms = client
-        .available_rooms(day, group_size)
-        .await
-        .map_err(|e| e.to_string())?;
-    Ok(rooms)
+) -> Result<Option<Snapshot>, CommandError> {
+    if let Snapshots(Some(store)) = snapshots {
+        let latest = store
+            .latest(date, Some(group_size))
+            .map_err(|e| CommandError::Database {
+                message: e.to_string(),
+            })?;
+        if latest.is_some() {
+            return Ok(latest);
+        }
+    }
+    let Some(server) = settings.library_data_source().remote_client() else {
+        return Ok(None);
+    };
+    match server.latest_rooms(date, group_size).await {
+        Ok(snapshot) => Ok(Some(snapshot)),
+        Err(RemoteError::Api { status, .. }) if status.as_u16() == 404 => Ok(None),
+        Err(e) => Err(CommandError::server(e)),
+    }
+}
+
+/// The most recently recorded rooms for the day, ranked like the scraped ones.
+#[tauri::command]
+#[specta::specta]
+async fn last_known_rooms(
+    settings: tauri::State<'_, SettingsStore>,
+    snapshots: tauri::State<'_, Snapshots>,
+    profile: tauri::State<'_, Profile>,
+    date: NaiveDate,
+    group_size: u8,
+) -> Result<Option<Snapshot>, CommandError> {
+    let mut latest = latest_snapshot(&settings, &snapshots, date, group_size).await?;
+    if let Some(snapshot) = &mut latest {
+        profile.rank(&mut snapshot.rooms);
+    }
+    Ok(latest)
+}
+
+/// The expected number of attendees of the default profile, until another one is chosen.
+#[tauri::command]
+#[specta::specta]
+fn default_group_size(profile: tauri::State<'_, Profile>) -> u8 {
+    profile.group_size
+}
+
+/// Where the rooms are checked, as it was last chosen.
+#[tauri::command]
+#[specta::specta]
+fn library_data_source(settings: tauri::State<'_, SettingsStore>) -> LibraryDataSource {
+    settings.library_data_source()
+}
+
+/// Checks the rooms through `source` from now on, including after a restart of the app.
+#[tauri::command]
+#[specta::specta]
+fn set_library_data_source(
+    settings: tauri::State<'_, SettingsStore>,
+    source: LibraryDataSource,
+) -> Result<(), CommandError> {
+    settings.set_library_data_source(source)
+}
+
+/// The WebDriver server of the app, without connecting to it or waiting for a running check.
+#[tauri::command]
+#[specta::specta]
+fn web_driver_connection(connection: tauri::State<'_, Connection>) -> ConnectionStatus {
+    connection.status()
+}
+
+/// Connects to the WebDriver server unless the open session still responds.
+#[tauri::command]
+#[specta::specta]
+async fn check_web_driver_connection(
+    connection: tauri::State<'_, Connection>,
+) -> Result<ConnectionStatus, CommandError> {
+    connection.check().await
+}
+
+/// Connects to another WebDriver server, e.g. one that was started on another port.
+#[tauri::command]
+#[specta::specta]
+async fn set_web_driver(
+    connection: tauri::State<'_, Connection>,
+    host: String,
+    port: u16,
+) -> Result<ConnectionStatus, CommandError> {
+    connection.reconnect(WebDriverEndpoint { host, port }).await
 }
 
 fn main() {
+    // The same WebDriver server and profile as the command-line app and the crawling server
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("{e}, the default configuration is used");
+        Config::default()
+    });
+    let profile = config.profile(None).unwrap_or_else(|e| {
+        eprintln!("{e}, the default profile is used");
+        Profile::default()
+    });
+    let snapshots = default_database_path().and_then(|path| {
+        SqliteSnapshotStore::open(&path)
+            .inspect_err(|e| eprintln!("Failed to open {path:?}: {e}"))
+            .ok()
+    });
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![available_rooms])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .manage(Connection::new(config.web_driver))
+        .manage(Snapshots(snapshots))
+        .manage(profile)
+        .setup(|app| {
+            let path = app
+                .path_resolver()
+                .app_config_dir()
+                .map(|dir| dir.join("settings.json"));
+            app.manage(SettingsStore::load(path));
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            available_rooms,
+            available_rooms_between,
+            last_known_rooms,
+            default_group_size,
+            library_data_source,
+            set_library_data_source,
+            web_driver_connection,
+            check_web_driver_connection,
+            set_web_driver
+        ])
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                // Returns right away if a scrape is still running
+                tauri::async_runtime::block_on(app.state::<Connection>().close());
+            }
+        });
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::available_rooms;
+    use specta::{DefOpts, Type, TypeDefs};
+
+    use crate::{
+        available_rooms, available_rooms_between, check_web_driver_connection, default_group_size,
+        error::CommandError, last_known_rooms, library_data_source, progress::ScrapeEvent,
+        set_library_data_source, set_web_driver, web_driver_connection,
+    };
 
     #[test]
     fn generate_bidings() {
+        // The errors of the commands and the payloads of the events aren't part of
+        // the signatures of the commands in the bindings
+        let mut type_map = TypeDefs::default();
+        for reference in [CommandError::reference, ScrapeEvent::reference] {
+            let opts = DefOpts {
+                parent_inline: false,
+                type_map: &mut type_map,
+            };
+            reference(opts, &[]).unwrap();
+        }
         tauri_specta::ts::export(
-            specta::collect_types![available_rooms],
+            specta::collect_types![
+                type_map: type_map,
+                available_rooms,
+                available_rooms_between,
+                last_known_rooms,
+                default_group_size,
+                library_data_source,
+                set_library_data_source,
+                web_driver_connection,
+                check_web_driver_connection,
+                set_web_driver
+            ],
             "../bindings/bindings.ts",
         )
         .unwrap();
@@ -0,0 +1,88 @@
+use calgary_central_library::{Availability, Room, ScrapeProgress};
+use chrono::NaiveDate;
+use serde::Serialize;
+use tauri::Window;
+
+use crate::error::CommandError;
+
+/// The event with a [`ScrapeEvent`] for every step of checking the rooms of a day.
+pub(crate) const SCRAPE_EVENT: &str = "scrape";
+
+/// A step of checking the rooms of a day for a group, emitted to the window that asked for them.
+#[derive(Clone, Serialize, specta::Type)]
+pub(crate) struct ScrapeEvent {
+    pub(crate) date: NaiveDate,
+    pub(crate) group_size: u8,
+    pub(crate) stage: ScrapeStage,
+}
+
+#[derive(Clone, Serialize, specta::Type)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum ScrapeStage {
+    /// Waiting for the WebDriver session, which is opened first if needed.
+    Connecting,
+    PageLoaded {
+        total: u32,
+    },
+    /// The room at `index` (counting from zero) out of `total` has been parsed.
+    RoomParsed {
+        index: u32,
+        total: u32,
+        room: Room,
+        availability: Availability,
+    },
+    /// The session was lost and the rooms are checked again with a new one,
+    /// so the rooms parsed so far are reported again.
+    Restarted,
+    Done,
+    Failed {
+        error: CommandError,
+    },
+}
+
+/// Emits the steps of checking the rooms of a day.
+pub(crate) struct Progress {
+    window: Window,
+    date: NaiveDate,
+    group_size: u8,
+}
+
+impl Progress {
+    pub(crate) fn new(window: Window, date: NaiveDate, group_size: u8) -> Self {
+        Self {
+            window,
+            date,
+            group_size,
+        }
+    }
+
+    pub(crate) fn emit(&self, stage: ScrapeStage) {
+        let event = ScrapeEvent {
+            date: self.date,
+            group_size: self.group_size,
+            stage,
+        };
+        if let Err(e) = self.window.emit(SCRAPE_EVENT, event) {
+            eprintln!("Failed to emit the progress of the scrape: {e}");
+        }
+    }
+
+    pub(crate) fn scraped(&self, progress: ScrapeProgress<'_>) {
+        self.emit(match progress {
+            ScrapeProgress::PageLoaded { total } => ScrapeStage::PageLoaded {
+                total: total as u32,
+            },
+            ScrapeProgress::RoomParsed {
+                index,
+                total,
+                room,
+                availability,
+            } => ScrapeStage::RoomParsed {
+                index: index as u32,
+                total: total as u32,
+                room: room.clone(),
+                availability: availability.clone(),
+            },
+        });
+    }
+}
@@ -0,0 +1,88 @@
+use std::{path::PathBuf, sync::Mutex};
+
+use calgary_central_library::RemoteClient;
+use serde::{Deserialize, Serialize};
+
+use crate::error::CommandError;
+
+/// Where the desktop app checks the rooms.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum LibraryDataSource {
+    /// The WebDriver session of the app.
+    #[default]
+    TauriWebview,
+    /// A running crawling server (`ccl_headless_browser_server`), so that no chromedriver is needed.
+    CrawlingServer { host: String, port: u16 },
+}
+
+/// The contents of the settings file.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    library_data_source: LibraryDataSource,
+}
+
+/// The settings that the app keeps between runs, in a JSON file in its configuration directory.
+pub(crate) struct SettingsStore {
+    /// Nothing is saved if the configuration directory is unknown.
+    path: Option<PathBuf>,
+    settings: Mutex<Settings>,
+}
+
+impl LibraryDataSource {
+    /// The client of the crawling server, if the rooms are checked by one.
+    pub(crate) fn remote_client(&self) -> Option<RemoteClient> {
+        match self {
+            LibraryDataSource::TauriWebview => None,
+            LibraryDataSource::CrawlingServer { host, port } => {
+                Some(RemoteClient::new(format!("http://{host}:{port}")))
+            }
+        }
+    }
+}
+
+impl SettingsStore {
+    /// Reads the settings from `path`, or uses the defaults if they can't be read.
+    pub(crate) fn load(path: Option<PathBuf>) -> Self {
+        let settings = path
+            .as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| {
+                let file = std::fs::read_to_string(path)
+                    .inspect_err(|e| eprintln!("Failed to read {path:?}: {e}"))
+                    .ok()?;
+                serde_json::from_str(&file)
+                    .inspect_err(|e| eprintln!("Failed to parse {path:?}: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default();
+        Self {
+            path,
+            settings: Mutex::new(settings),
+        }
+    }
+
+    pub(crate) fn library_data_source(&self) -> LibraryDataSource {
+        self.settings.lock().unwrap().library_data_source.clone()
+    }
+
+    pub(crate) fn set_library_data_source(
+        &self,
+        source: LibraryDataSource,
+    ) -> Result<(), CommandError> {
+        let mut settings = self.settings.lock().unwrap();
+        settings.library_data_source = source;
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let failed = |e: &dyn std::fmt::Display| CommandError::Settings {
+            message: format!("{path:?}: {e}"),
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| failed(&e))?;
+        }
+        let json = serde_json::to_string_pretty(&*settings).map_err(|e| failed(&e))?;
+        std::fs::write(path, json).map_err(|e| failed(&e))
+    }
+}